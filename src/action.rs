@@ -0,0 +1,258 @@
+use crate::CTypeTransformCoordinates;
+use crate::InputDevice;
+use crate::config::ACTION_DEBOUNCE;
+use crate::config::ACTION_MAX_COMMANDS;
+use crate::config::ACTION_RATE_LIMIT;
+use crate::model::Contact;
+use crate::mouse::MouseButton;
+use crate::mouse::MouseMode;
+use enclose::enc;
+use log::error;
+use log::info;
+use log::warn;
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::thread::spawn;
+use std::time::Instant;
+use tfc::Context;
+use tfc::Key;
+use tfc::KeyboardContext;
+use tfc::MouseContext;
+use uinput::Device;
+use uinput::event::Keyboard;
+
+/// What a binding does, see `config::GESTURE_BINDINGS`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+	// Keys are pressed in order and released in reverse order, through `tfc`.
+	Keys(&'static [Key]),
+	// The same through a virtual uinput keyboard, works without X11.
+	UInputKeys(&'static [Keyboard]),
+	Mouse(MouseButton),
+	// Executed with `sh -c`, contacts are passed in `UTOUCH_*` environment variables.
+	Command(&'static str),
+	// Stop or resume passing contacts to the backend, gestures keep working.
+	ToggleInput,
+	Transform(CTypeTransformCoordinates),
+	MouseMode(MouseMode),
+}
+
+/// Executes actions with debounce and rate limiting, devices are created on first use.
+pub struct ActionRunner {
+	context: Option<Context>,
+	keyboard: Option<Device>,
+
+	is_input_enabled: bool,
+
+	// (action address, time) of the last run of every action.
+	last_runs: Vec<(usize, Instant)>,
+	// Start times of the runs within `ACTION_RATE_LIMIT` window.
+	runs: Vec<Instant>,
+	commands: Arc<AtomicUsize>,
+}
+
+impl ActionRunner {
+	pub fn new() -> Self {
+		Self {
+			context: None,
+			keyboard: None,
+
+			is_input_enabled: true,
+
+			last_runs: Vec::new(),
+			runs: Vec::new(),
+			commands: Arc::new(AtomicUsize::new(0)),
+		}
+	}
+
+	#[inline]
+	pub const fn is_input_enabled(&self) -> bool {
+		self.is_input_enabled
+	}
+
+	fn context(&mut self) -> anyhow::Result<&mut Context> {
+		if self.context.is_none() {
+			self.context = Some(Context::new()?);
+		}
+
+		Ok(self.context.as_mut().unwrap())
+	}
+
+	fn keyboard(&mut self) -> anyhow::Result<&mut Device> {
+		if self.keyboard.is_none() {
+			let device = uinput::default()?
+				.name("utouch_rs_keyboard")?
+				.event(Keyboard::All)?
+				.create()?;
+			self.keyboard = Some(device);
+		}
+
+		Ok(self.keyboard.as_mut().unwrap())
+	}
+
+	fn is_allowed(&mut self, action: &'static Action, now: Instant) -> bool {
+		let key = action as *const Action as usize;
+		let last_run = self.last_runs.iter().position(|(a, _)| *a == key);
+		if let Some(n) = last_run
+			&& now.saturating_duration_since(self.last_runs[n].1) < ACTION_DEBOUNCE
+		{
+			return false;
+		}
+
+		let (count, window) = ACTION_RATE_LIMIT;
+		self.runs
+			.retain(|a| now.saturating_duration_since(*a) < window);
+		if self.runs.len() >= count {
+			return false;
+		}
+
+		// Both limits passed, only now the run counts.
+		self.runs.push(now);
+		match last_run {
+			Some(n) => self.last_runs[n].1 = now,
+			None => self.last_runs.push((key, now)),
+		}
+
+		true
+	}
+
+	fn command(&self, command: &str, frame: &[Contact]) -> anyhow::Result<()> {
+		if self.commands.load(Ordering::Relaxed) >= ACTION_MAX_COMMANDS {
+			warn!("#[action] too many running commands, skip: {:?}", command);
+			return Ok(());
+		}
+
+		let mut cmd = Command::new("sh");
+		cmd.arg("-c").arg(command);
+
+		// Display orientation, raw units.
+		let contacts: Vec<String> = frame
+			.iter()
+			.map(|a| {
+				let (x, y) = a.screen_pos();
+				format!("{},{}", x, y)
+			})
+			.collect();
+		if let Some((x, y)) = frame
+			.iter()
+			.find(|a| a.role.is_primary())
+			.map(Contact::screen_pos)
+		{
+			cmd.env("UTOUCH_X", x.to_string())
+				.env("UTOUCH_Y", y.to_string());
+		}
+		cmd.env("UTOUCH_FINGERS", frame.len().to_string())
+			.env("UTOUCH_CONTACTS", contacts.join(" "));
+
+		let mut child = cmd.spawn()?;
+		self.commands.fetch_add(1, Ordering::Relaxed);
+		spawn(enc!((self.commands => commands) move || {
+			if let Err(e) = child.wait() {
+				error!("#[action] wait command, err: {:?}", e);
+			}
+			commands.fetch_sub(1, Ordering::Relaxed);
+		}));
+
+		Ok(())
+	}
+
+	pub fn run(
+		&mut self,
+		action: &'static Action,
+		frame: &[Contact],
+		input_device: &mut InputDevice,
+		now: Instant,
+	) -> anyhow::Result<()> {
+		if !self.is_allowed(action, now) {
+			info!("#[action] {:?}, rate limited", action);
+			return Ok(());
+		}
+		info!("#[action] {:?}", action);
+
+		match *action {
+			Action::Keys(keys) => {
+				let context = self.context()?;
+				for key in keys.iter() {
+					context.key_down(*key)?;
+				}
+				for key in keys.iter().rev() {
+					context.key_up(*key)?;
+				}
+			}
+			Action::UInputKeys(keys) => {
+				let keyboard = self.keyboard()?;
+				for key in keys.iter() {
+					keyboard.press(key)?;
+				}
+				keyboard.synchronize()?;
+				for key in keys.iter().rev() {
+					keyboard.release(key)?;
+				}
+				keyboard.synchronize()?;
+			}
+			Action::Mouse(button) => {
+				let button = match button {
+					MouseButton::Left => tfc::MouseButton::Left,
+					MouseButton::Right => tfc::MouseButton::Right,
+					MouseButton::Middle => tfc::MouseButton::Middle,
+				};
+				self.context()?.mouse_click(button)?;
+			}
+			Action::Command(command) => self.command(command, frame)?,
+			Action::ToggleInput => {
+				self.is_input_enabled = !self.is_input_enabled;
+				info!("#[action] input enabled: {}", self.is_input_enabled);
+				if !self.is_input_enabled {
+					input_device.cancel()?;
+				}
+			}
+			Action::Transform(transform) => input_device.set_transform(transform),
+			Action::MouseMode(mode) => input_device.set_mouse_mode(mode)?,
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_action() {
+	use std::time::Duration;
+
+	static A: Action = Action::ToggleInput;
+	static B: Action = Action::Command("true");
+
+	let mut runner = ActionRunner::new();
+	let start = Instant::now();
+
+	// Debounce is per action.
+	assert!(runner.is_allowed(&A, start));
+	assert!(!runner.is_allowed(&A, start + ACTION_DEBOUNCE / 2));
+	assert!(runner.is_allowed(&B, start + ACTION_DEBOUNCE / 2));
+	assert!(runner.is_allowed(&A, start + ACTION_DEBOUNCE));
+
+	// Rate limit is global.
+	let (count, window) = ACTION_RATE_LIMIT;
+	let mut runner = ActionRunner::new();
+	let mut now = start;
+	for _ in 0..count {
+		assert!(runner.is_allowed(&A, now));
+		now += ACTION_DEBOUNCE;
+	}
+	if ACTION_DEBOUNCE * count as u32 <= window {
+		assert!(!runner.is_allowed(&A, now));
+	}
+	assert!(runner.is_allowed(&A, now + window + Duration::from_millis(1)));
+
+	// An action rejected by the rate limit does not start its debounce.
+	static OTHERS: [Action; 8] = [const { Action::ToggleInput }; 8];
+	let mut runner = ActionRunner::new();
+	for a in OTHERS.iter().take(count) {
+		assert!(runner.is_allowed(a, start));
+	}
+	assert!(!runner.is_allowed(&A, start + window - Duration::from_millis(1)));
+	assert!(runner.is_allowed(&A, start + window));
+}
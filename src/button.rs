@@ -0,0 +1,320 @@
+use crate::config::BUTTON_LONG_PRESS_DURATION;
+use crate::config::BUTTON_REGIONS;
+use crate::config::BUTTON_REPEAT_DELAY;
+use crate::config::BUTTON_REPEAT_INTERVAL;
+use crate::model::Contact;
+use log::info;
+use log::trace;
+use std::time::Instant;
+use uinput::Device;
+use uinput::event::Keyboard;
+
+/// Area in raw units, button regions use raw sensor coordinates (as in `trace` logs, before `ACTIVE_AREA`).
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+	Rect {
+		x: u16,
+		y: u16,
+		width: u16,
+		height: u16,
+	},
+	Circle {
+		x: u16,
+		y: u16,
+		radius: u16,
+	},
+}
+
+impl Region {
+	#[inline]
+	pub const fn center(&self) -> (u16, u16) {
+		match *self {
+			Self::Rect {
+				x,
+				y,
+				width,
+				height,
+			} => (x + width / 2, y + height / 2),
+			Self::Circle { x, y, .. } => (x, y),
+		}
+	}
+
+	pub const fn contains(&self, px: u16, py: u16) -> bool {
+		match *self {
+			Self::Rect {
+				x,
+				y,
+				width,
+				height,
+			} => px >= x && py >= y && px - x <= width && py - y <= height,
+			Self::Circle { x, y, radius } => {
+				let (dx, dy) = (px.abs_diff(x) as u32, py.abs_diff(y) as u32);
+				dx * dx + dy * dy <= radius as u32 * radius as u32
+			}
+		}
+	}
+}
+
+/// A printed icon on the sensor, see `config::BUTTON_REGIONS`.
+#[derive(Debug, Clone, Copy)]
+pub struct ButtonRegion {
+	pub region: Region,
+	pub key: Keyboard,
+	// Emitted instead of `key` once the button is held for `BUTTON_LONG_PRESS_DURATION`,
+	// `key` is then only clicked on a short release.
+	pub long_press: Option<Keyboard>,
+	// Auto-repeat of the pressed key while held.
+	pub repeat: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ButtonState {
+	Released,
+	// Waiting for release or long press, nothing emitted yet.
+	Pending { since: Instant },
+	Pressed { key: Keyboard, next_repeat: Instant },
+}
+
+/// Key event with the evdev value: 0 - release, 1 - press, 2 - repeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+	pub key: Keyboard,
+	pub value: i32,
+}
+
+impl KeyEvent {
+	#[inline]
+	const fn new(key: Keyboard, value: i32) -> Self {
+		Self { key, value }
+	}
+}
+
+/// Turns contacts within button regions into key events, the contacts do not reach
+/// the touchscreen or mouse backends.
+pub struct VirtualButtons {
+	buttons: &'static [ButtonRegion],
+	states: Vec<ButtonState>,
+	keyboard: Option<Device>,
+
+	events: Vec<KeyEvent>,
+}
+
+impl VirtualButtons {
+	pub fn from_config() -> anyhow::Result<Self> {
+		let mut result = Self::new(BUTTON_REGIONS);
+		if !BUTTON_REGIONS.is_empty() {
+			info!("#[button] regions: {}", BUTTON_REGIONS.len());
+			let mut device = uinput::default()?.name("utouch_rs_buttons")?;
+			for button in BUTTON_REGIONS.iter() {
+				device = device.event(button.key)?;
+				if let Some(key) = button.long_press {
+					device = device.event(key)?;
+				}
+			}
+			result.keyboard = Some(device.create()?);
+		}
+
+		Ok(result)
+	}
+
+	pub fn new(buttons: &'static [ButtonRegion]) -> Self {
+		Self {
+			buttons,
+			states: vec![ButtonState::Released; buttons.len()],
+			keyboard: None,
+
+			events: Vec::new(),
+		}
+	}
+
+	/// Some button is held, `tick` is needed between frames for long press and repeat.
+	#[inline]
+	pub fn is_held(&self) -> bool {
+		self.states.iter().any(|a| *a != ButtonState::Released)
+	}
+
+	fn update(&mut self, frame: &[Contact], now: Instant) {
+		for (button, state) in self.buttons.iter().zip(self.states.iter_mut()) {
+			let is_touched = frame
+				.iter()
+				.any(|a| !a.is_hovering && button.region.contains(a.x, a.y));
+
+			*state = match (*state, is_touched) {
+				(ButtonState::Released, true) => match button.long_press {
+					Some(_) => ButtonState::Pending { since: now },
+					None => {
+						self.events.push(KeyEvent::new(button.key, 1));
+						ButtonState::Pressed {
+							key: button.key,
+							next_repeat: now + BUTTON_REPEAT_DELAY,
+						}
+					}
+				},
+				(ButtonState::Released, false) => ButtonState::Released,
+				(ButtonState::Pending { .. }, false) => {
+					self.events.push(KeyEvent::new(button.key, 1));
+					self.events.push(KeyEvent::new(button.key, 0));
+					ButtonState::Released
+				}
+				(ButtonState::Pending { since }, true) => match button.long_press {
+					Some(key)
+						if now.saturating_duration_since(since) >= BUTTON_LONG_PRESS_DURATION =>
+					{
+						self.events.push(KeyEvent::new(key, 1));
+						ButtonState::Pressed {
+							key,
+							next_repeat: now + BUTTON_REPEAT_DELAY,
+						}
+					}
+					_ => ButtonState::Pending { since },
+				},
+				(ButtonState::Pressed { key, .. }, false) => {
+					self.events.push(KeyEvent::new(key, 0));
+					ButtonState::Released
+				}
+				(ButtonState::Pressed { key, next_repeat }, true) => {
+					if button.repeat && now >= next_repeat {
+						self.events.push(KeyEvent::new(key, 2));
+						ButtonState::Pressed {
+							key,
+							next_repeat: now + BUTTON_REPEAT_INTERVAL,
+						}
+					} else {
+						ButtonState::Pressed { key, next_repeat }
+					}
+				}
+			};
+		}
+	}
+
+	fn flush(&mut self) -> anyhow::Result<()> {
+		if self.events.is_empty() {
+			return Ok(());
+		}
+		trace!("#[button] {:?}", self.events);
+
+		if let Some(ref mut keyboard) = self.keyboard {
+			for event in self.events.iter() {
+				keyboard.send(event.key, event.value)?;
+			}
+			keyboard.synchronize()?;
+		}
+		self.events.clear();
+
+		Ok(())
+	}
+
+	/// Emits key events and removes contacts within button regions from `frame`.
+	pub fn process(&mut self, frame: &mut Vec<Contact>, now: Instant) -> anyhow::Result<()> {
+		if self.buttons.is_empty() {
+			return Ok(());
+		}
+
+		self.update(frame, now);
+		let buttons = self.buttons;
+		frame.retain(|a| !buttons.iter().any(|b| b.region.contains(a.x, a.y)));
+
+		self.flush()
+	}
+
+	/// Long press and repeat while the controller is quiet, the held contacts are unchanged.
+	pub fn tick(&mut self, now: Instant) -> anyhow::Result<()> {
+		for (button, state) in self.buttons.iter().zip(self.states.iter_mut()) {
+			match *state {
+				ButtonState::Pending { since } => {
+					if let Some(key) = button.long_press
+						&& now.saturating_duration_since(since) >= BUTTON_LONG_PRESS_DURATION
+					{
+						self.events.push(KeyEvent::new(key, 1));
+						*state = ButtonState::Pressed {
+							key,
+							next_repeat: now + BUTTON_REPEAT_DELAY,
+						};
+					}
+				}
+				ButtonState::Pressed { key, next_repeat }
+					if button.repeat && now >= next_repeat =>
+				{
+					self.events.push(KeyEvent::new(key, 2));
+					*state = ButtonState::Pressed {
+						key,
+						next_repeat: now + BUTTON_REPEAT_INTERVAL,
+					};
+				}
+				_ => {}
+			}
+		}
+
+		self.flush()
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_button() {
+	use std::time::Duration;
+	use uinput::event::keyboard::Key;
+	use uinput::event::keyboard::Misc;
+
+	static BUTTONS: &[ButtonRegion] = &[
+		ButtonRegion {
+			region: Region::Rect {
+				x: 0,
+				y: 0,
+				width: 100,
+				height: 100,
+			},
+			key: Keyboard::Misc(Misc::VolumeUp),
+			long_press: None,
+			repeat: true,
+		},
+		ButtonRegion {
+			region: Region::Circle {
+				x: 500,
+				y: 50,
+				radius: 40,
+			},
+			key: Keyboard::Misc(Misc::Back),
+			long_press: Some(Keyboard::Key(Key::Home)),
+			repeat: false,
+		},
+	];
+	let mut buttons = VirtualButtons::new(BUTTONS);
+	let mut now = Instant::now();
+
+	// Volume: press, repeat, release; the contact does not pass.
+	let mut frame = vec![Contact::new(0, 50, 50), Contact::new(0, 1000, 1000)];
+	buttons.process(&mut frame, now).unwrap();
+	assert_eq!(frame, [Contact::new(0, 1000, 1000)]);
+	assert!(buttons.is_held());
+	now += BUTTON_REPEAT_DELAY;
+	buttons.update(&[Contact::new(0, 50, 50)], now);
+	now += BUTTON_REPEAT_INTERVAL / 2;
+	buttons.update(&[Contact::new(0, 50, 50)], now);
+	buttons.update(&[], now);
+	let key = Keyboard::Misc(Misc::VolumeUp);
+	assert_eq!(
+		buttons.events,
+		[KeyEvent::new(key, 2), KeyEvent::new(key, 0)]
+	);
+	buttons.events.clear();
+
+	// Back: short tap clicks on release, long press emits Home.
+	let back = Keyboard::Misc(Misc::Back);
+	buttons.update(&[Contact::new(0, 510, 60)], now);
+	assert!(buttons.events.is_empty());
+	buttons.update(&[], now + Duration::from_millis(100));
+	assert_eq!(
+		buttons.events,
+		[KeyEvent::new(back, 1), KeyEvent::new(back, 0)]
+	);
+	buttons.events.clear();
+
+	let home = Keyboard::Key(Key::Home);
+	buttons.update(&[Contact::new(0, 510, 60)], now);
+	buttons.tick(now + BUTTON_LONG_PRESS_DURATION).unwrap();
+	buttons.update(&[], now + BUTTON_LONG_PRESS_DURATION * 2);
+	assert_eq!(buttons.events, [KeyEvent::new(home, 0)]);
+	assert!(!buttons.is_held());
+}
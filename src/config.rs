@@ -1,3 +1,14 @@
+use crate::action::Action;
+use crate::button::ButtonRegion;
+use crate::gamepad::PadRegion;
+use crate::gesture::Gesture;
+use crate::net::NetTransport;
+use crate::pen::PressureCurve;
+use crate::pipeline::area::EdgeCurve;
+use crate::pipeline::filter::JitterFilter;
+use crate::source::serial::SerialFraming;
+use std::time::Duration;
+
 // GPIO4 pin number for interrupt signal. TODO: Verify correct pin.
 pub const RPPAL_INT_PIN: u8 = 4;
 // GPIO17 pin number for response signal. TODO: Verify correct pin.
@@ -16,3 +27,194 @@ pub const DEFAULT_I2C_BUS: u8 = 1;
 
 // Flag indicating that the coordinate should be inverted.
 pub const NEEDS_COORDINATE_INVERSION: bool = true;
+
+// Raw coordinate range reported by the sensor (before inversion).
+pub const SENSOR_MAX_X: u16 = if NEEDS_COORDINATE_INVERSION {
+	DISPLAY_HEIGHT as _
+} else {
+	DISPLAY_WIDTH as _
+};
+pub const SENSOR_MAX_Y: u16 = if NEEDS_COORDINATE_INVERSION {
+	DISPLAY_WIDTH as _
+} else {
+	DISPLAY_HEIGHT as _
+};
+
+// State byte (last byte of a chunk): in the captures every touching contact carries 0x40
+// (the first finger) or 0x80 (further fingers), 0x10 marks an update of a known contact.
+// Contacts with none of these bits are taken as hovering above the sensor. Unverified: no
+// capture has a hovering contact, so these may only be finger flags.
+pub const TOUCH_STATE_BITS: u8 = 0b1100_0000;
+// If true, hovering contacts are ABS_MT_DISTANCE on the uinput device, pointer motion
+// without button in mouse mode..., otherwise they are dropped right after decoding.
+// Off until a capture with real proximity data confirms `TOUCH_STATE_BITS`.
+pub const HOVER: bool = false;
+
+// Usable part of the sensor in raw coordinates (min_x, min_y, max_x, max_y),
+// it is stretched over the whole sensor range before reaching the backends.
+pub const ACTIVE_AREA: (u16, u16, u16, u16) = (0, 0, SENSOR_MAX_X, SENSOR_MAX_Y);
+// Bezel bands in raw coordinates (left, top, right, bottom), contacts inside them are dropped.
+pub const DEAD_ZONE: (u16, u16, u16, u16) = (0, 0, 0, 0);
+// If true, contacts outside the active area are clamped to it, otherwise dropped.
+pub const CLAMP_OUT_OF_RANGE: bool = true;
+// Edge compensation (left, top, right, bottom), see `pipeline::area::EdgeCurve`.
+pub const EDGE_CURVES: [EdgeCurve; 4] = [EdgeCurve::NONE; 4];
+
+// Per-contact jitter filter chain, applied in order after the active area (empty - disabled),
+// e.g. `&[JitterFilter::Hysteresis { threshold: 4 }]`.
+pub const JITTER_FILTERS: &[JitterFilter] = &[];
+
+// Contacts with a larger size are palms (0 - disabled).
+pub const PALM_MAX_SIZE: u8 = 0;
+// Maximum number of simultaneous contacts, extra contacts are rejected.
+pub const MAX_CONTACTS: usize = 10;
+// (band, size): contacts within `band` raw units from an edge and larger than `size` are palms
+// (band 0 - disabled).
+pub const PALM_EDGE: (u16, u8) = (0, 0);
+// (count, radius): `count` or more new contacts within `radius` raw units of each other are a palm
+// (count 0 - disabled).
+pub const PALM_CLUSTER: (usize, u16) = (0, 0);
+
+// A contact is reported only after it was present in this many frames...
+pub const GHOST_MIN_FRAMES: usize = 1;
+// ...and for at least this long.
+pub const GHOST_MIN_DURATION: Duration = Duration::ZERO;
+// Contacts moving faster (raw units per second) are phantoms (0 - disabled).
+pub const GHOST_MAX_VELOCITY: u32 = 0;
+// (count, window, mute): `count` new contacts within `window` mute the input for `mute`
+// (count 0 - disabled).
+pub const GHOST_BURST: (usize, Duration, Duration) = (0, Duration::ZERO, Duration::ZERO);
+
+// Maximum distance in raw units between frames for a contact to keep its tracking id.
+pub const IDENT_MAX_DISTANCE: u16 = 200;
+
+// Contacts lifted within this time without moving further than `TAP_MAX_DISTANCE` raw units
+// are a tap.
+pub const TAP_MAX_DURATION: Duration = Duration::from_millis(250);
+pub const TAP_MAX_DISTANCE: u16 = 20;
+// Second tap within this time near the first one is a double tap.
+pub const DOUBLE_TAP_INTERVAL: Duration = Duration::from_millis(350);
+// Single finger held this long within `LONG_PRESS_TOLERANCE` raw units is a long press
+// (ZERO - disabled).
+pub const LONG_PRESS_DURATION: Duration = Duration::from_millis(700);
+pub const LONG_PRESS_TOLERANCE: u16 = 30;
+
+// Wheel units (1/120 of a notch) per raw unit of two-finger movement.
+pub const SCROLL_SPEED: f32 = 4.0;
+// If true, the content follows the fingers as on a phone.
+pub const NATURAL_SCROLL: bool = true;
+// Change of the distance between two fingers in raw units per Ctrl+wheel zoom notch.
+pub const PINCH_STEP: u16 = 40;
+
+// Touchpad mode (CMOUSE=TOUCHPAD, CDEVICE=UINPUT_MOUSE): pointer units per raw unit...
+pub const TOUCHPAD_SPEED: f32 = 1.0;
+// ...multiplied by `1 + TOUCHPAD_ACCEL * (velocity - TOUCHPAD_ACCEL_THRESHOLD)` above the
+// threshold (raw units per second), up to `TOUCHPAD_MAX_GAIN`.
+pub const TOUCHPAD_ACCEL: f32 = 0.002;
+pub const TOUCHPAD_ACCEL_THRESHOLD: f32 = 300.0;
+pub const TOUCHPAD_MAX_GAIN: f32 = 3.0;
+
+// Evdev source (CSOURCE=EVDEV): touchscreen with a kernel driver, CEVDEV overrides the path...
+pub const EVDEV_PATH: &str = "/dev/input/event0";
+// ...grabbed, so that nothing else sees its contacts twice.
+pub const EVDEV_GRAB: bool = true;
+
+// Serial source (CSOURCE=SERIAL): controller bytes forwarded by a microcontroller,
+// CSERIAL overrides the path...
+pub const SERIAL_PATH: &str = "/dev/ttyACM0";
+pub const SERIAL_BAUD: u32 = 115200;
+pub const SERIAL_FRAMING: SerialFraming = SerialFraming::EIGHT_N1;
+// ...a pause this long drops a partially received line...
+pub const SERIAL_IDLE_TIMEOUT: Duration = Duration::from_millis(50);
+// ...and an unplugged port is reopened every interval.
+pub const SERIAL_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Network transport: the board (CDEVICE=NET for contacts, NET_RAW for the controller bytes)
+// sends to `NET_PEER`, the receiver (CSOURCE=NET) listens on `NET_LISTEN`, CNET overrides either.
+pub const NET_TRANSPORT: NetTransport = NetTransport::Tcp;
+pub const NET_PEER: &str = "192.168.1.2:7373";
+pub const NET_LISTEN: &str = "0.0.0.0:7373";
+// The board sends a keepalive after this long without a packet...
+pub const NET_KEEPALIVE: Duration = Duration::from_millis(250);
+// ...the receiver releases the contacts after this long without one...
+pub const NET_LINK_TIMEOUT: Duration = Duration::from_secs(1);
+// ...and the board reconnects (TCP) at most this often.
+pub const NET_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Physical size of the sensor in mm in the display orientation, reported as the resolution
+// of the libinput touchpad (CDEVICE=LIBINPUT) and the pen tablet (CDEVICE=PEN).
+pub const SENSOR_SIZE_MM: (u16, u16) = (154, 86);
+
+// Pen tablet (CDEVICE=PEN): contact sizes mapped to the lowest and the highest pressure...
+pub const PEN_SIZE_RANGE: (u8, u8) = (1, 40);
+// ...through this curve.
+pub const PEN_PRESSURE_CURVE: PressureCurve = PressureCurve::Linear;
+
+// Single-finger drag-to-scroll (CMOUSE=SCROLL) keeps scrolling after a fling.
+pub const KINETIC_SCROLL: bool = true;
+// Kinetic scroll velocity decays by `e` every `KINETIC_TIME_CONSTANT`...
+pub const KINETIC_TIME_CONSTANT: Duration = Duration::from_millis(325);
+// ...and stops below this many wheel units per second.
+pub const KINETIC_MIN_VELOCITY: f32 = 240.0;
+// How often animations (kinetic scroll) are advanced while no interrupt arrives.
+pub const ANIMATION_INTERVAL: Duration = Duration::from_millis(16);
+
+// Gesture bindings, for example:
+// (Gesture::Swipe { fingers: 3, direction: Direction::Left }, Action::Keys(&[Key::Alt, Key::LeftArrow])),
+// (Gesture::EdgeSwipe(Edge::Bottom), Action::Command("onboard")),
+// (Gesture::Hold { fingers: 4 }, Action::ToggleInput),
+// (Gesture::PinchIn { fingers: 3 }, Action::UInputKeys(&[Keyboard::Key(Key::LeftMeta), Keyboard::Key(Key::D)])),
+// (Gesture::Swipe { fingers: 4, direction: Direction::Up }, Action::Command("notify-send \"$UTOUCH_X $UTOUCH_Y\"")),
+pub const GESTURE_BINDINGS: &[(Gesture, Action)] = &[];
+// Templates recorded with `utouch_rs record-gesture <name>` are stored as `<name>.stroke`...
+pub const TEMPLATE_DIR: &str = "/etc/utouch_rs/gestures";
+// ...and bound by name, for example `("unlock", Action::Command("maintenance-menu"))`.
+// A template is matched once the contacts are lifted, the stroke itself still reaches the backend.
+pub const TEMPLATE_BINDINGS: &[(&str, Action)] = &[];
+// Score in 0.0..=1.0 a stroke needs to match a template...
+pub const TEMPLATE_MIN_SCORE: f32 = 0.85;
+// ...while rotated by up to this many degrees.
+pub const TEMPLATE_MAX_ROTATION: f32 = 15.0;
+// Minimum movement in raw units for swipes and edge swipes...
+pub const SWIPE_MIN_DISTANCE: u16 = 150;
+// ...edge swipes start within this band from the bezel.
+pub const EDGE_SWIPE_BAND: u16 = 30;
+// Change of the finger spread for pinch in/out.
+pub const PINCH_SCALE: f32 = 1.4;
+// Two-finger rotation in degrees.
+pub const ROTATE_MIN_ANGLE: f32 = 30.0;
+// Fingers held this long within `HOLD_TOLERANCE` raw units.
+pub const HOLD_DURATION: Duration = Duration::from_millis(800);
+pub const HOLD_TOLERANCE: u16 = 30;
+
+// The same action does not run again within this time...
+pub const ACTION_DEBOUNCE: Duration = Duration::from_millis(500);
+// ...at most `count` actions run within `window`...
+pub const ACTION_RATE_LIMIT: (usize, Duration) = (5, Duration::from_secs(1));
+// ...and at most this many commands run at the same time.
+pub const ACTION_MAX_COMMANDS: usize = 4;
+
+// Printed icons outside the display, in raw sensor coordinates, for example:
+// ButtonRegion { region: Region::Rect { x: 0, y: 0, width: 120, height: 80 }, key: Keyboard::Misc(Misc::Back), long_press: Some(Keyboard::Key(Key::Home)), repeat: false },
+// ButtonRegion { region: Region::Circle { x: 60, y: 400, radius: 50 }, key: Keyboard::Misc(Misc::VolumeUp), long_press: None, repeat: true },
+pub const BUTTON_REGIONS: &[ButtonRegion] = &[];
+// Auto-repeat of a held button starts after the delay and repeats every interval.
+pub const BUTTON_REPEAT_DELAY: Duration = Duration::from_millis(500);
+pub const BUTTON_REPEAT_INTERVAL: Duration = Duration::from_millis(100);
+// A button held this long emits its `long_press` key, independent of `LONG_PRESS_DURATION`.
+pub const BUTTON_LONG_PRESS_DURATION: Duration = Duration::from_millis(700);
+
+// Controls of the virtual gamepad (CDEVICE=GAMEPAD), in the display orientation, for example:
+// PadRegion { region: Region::Rect { x: 0, y: 300, width: 500, height: 500 }, control: PadControl::Stick { stick: Stick::Left, dead_zone: 15, radius: 120 } },
+// PadRegion { region: Region::Circle { x: 1100, y: 600, radius: 70 }, control: PadControl::Button(GamePad::South) },
+pub const GAMEPAD_REGIONS: &[PadRegion] = &[];
+
+// USB HID gadget (CDEVICE=HID_GADGET): name of the gadget in the configfs...
+pub const GADGET_NAME: &str = "utouch_rs";
+// ...the USB device controller, `None` - the first one in /sys/class/udc
+// (`modprobe dummy_hcd` for a local loopback)...
+pub const GADGET_UDC: Option<&str> = None;
+// ...(idVendor, idProduct), Linux Foundation multifunction composite gadget by default...
+pub const GADGET_IDS: (u16, u16) = (0x1d6b, 0x0104);
+// ...(manufacturer, product, serial number).
+pub const GADGET_STRINGS: (&str, &str, &str) = ("utouch_rs", "utouch_rs touchscreen", "0");
@@ -0,0 +1,310 @@
+use crate::button::Region;
+use crate::config::GAMEPAD_REGIONS;
+use crate::model::Contact;
+use log::info;
+use uinput::Device;
+use uinput::event::absolute::Hat;
+use uinput::event::absolute::Position;
+use uinput::event::controller::GamePad;
+
+// Range of the analog stick axes, centred at zero.
+pub const AXIS_MAX: i32 = 32767;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stick {
+	// ABS_X, ABS_Y
+	Left,
+	// ABS_RX, ABS_RY
+	Right,
+}
+
+impl Stick {
+	#[inline]
+	pub const fn axes(&self) -> (Position, Position) {
+		match self {
+			Self::Left => (Position::X, Position::Y),
+			Self::Right => (Position::RX, Position::RY),
+		}
+	}
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PadControl {
+	// Centred where the finger lands, full tilt at `radius` raw units from there.
+	Stick {
+		stick: Stick,
+		dead_zone: u16,
+		radius: u16,
+	},
+	// Hat 0, direction from the centre of the region.
+	DPad {
+		dead_zone: u16,
+	},
+	Button(GamePad),
+}
+
+/// A control on the screen, the region is in the display orientation (`Contact::screen_pos`).
+#[derive(Debug, Clone, Copy)]
+pub struct PadRegion {
+	pub region: Region,
+	pub control: PadControl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PadEvent {
+	Axis(Position, i32),
+	Hat(Hat, i32),
+	Button(GamePad, bool),
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ControlState {
+	// Tracking id of the finger that landed on the control, it keeps the control
+	// while it moves out of the region.
+	owner: Option<u16>,
+	origin: (i32, i32),
+	value: (i32, i32),
+}
+
+/// Maps contacts to sticks, a d-pad and buttons, every finger drives its own control.
+pub struct GamepadEmulator {
+	regions: &'static [PadRegion],
+	states: Vec<ControlState>,
+}
+
+impl GamepadEmulator {
+	pub fn new(regions: &'static [PadRegion]) -> Self {
+		Self {
+			regions,
+			states: vec![ControlState::default(); regions.len()],
+		}
+	}
+
+	/// Virtual controller with the axes and buttons of `GAMEPAD_REGIONS`.
+	pub fn create_device(name: String) -> anyhow::Result<Device> {
+		info!("#[gamepad] regions: {}", GAMEPAD_REGIONS.len());
+		let mut device = uinput::default()?.name(name)?;
+
+		// Always a south button, so that the device is classified as a gamepad.
+		device = device.event(GamePad::South)?;
+		for a in GAMEPAD_REGIONS.iter() {
+			if let PadControl::Button(button) = a.control
+				&& button != GamePad::South
+			{
+				device = device.event(button)?;
+			}
+		}
+		for (x, y) in [Stick::Left.axes(), Stick::Right.axes()] {
+			device = device
+				.event(x)?
+				.min(-AXIS_MAX)
+				.max(AXIS_MAX)
+				.event(y)?
+				.min(-AXIS_MAX)
+				.max(AXIS_MAX);
+		}
+		device = device
+			.event(Hat::X0)?
+			.min(-1)
+			.max(1)
+			.event(Hat::Y0)?
+			.min(-1)
+			.max(1);
+
+		Ok(device.create()?)
+	}
+
+	fn stick_value(dx: i32, dy: i32, dead_zone: u16, radius: u16) -> (i32, i32) {
+		let distance = (dx as f32).hypot(dy as f32);
+		let dead_zone = dead_zone as f32;
+		if distance <= dead_zone {
+			return (0, 0);
+		}
+
+		let tilt = ((distance - dead_zone) / (radius as f32 - dead_zone).max(1.0)).min(1.0);
+		let scale = tilt * AXIS_MAX as f32 / distance;
+		((dx as f32 * scale) as i32, (dy as f32 * scale) as i32)
+	}
+
+	fn dpad_value(dx: i32, dy: i32, dead_zone: u16) -> (i32, i32) {
+		// Diagonals within +-22.5 degrees press both directions.
+		let axis = |a: i32, b: i32| {
+			if a.unsigned_abs() > dead_zone as u32 && a.unsigned_abs() * 5 >= b.unsigned_abs() * 2 {
+				a.signum()
+			} else {
+				0
+			}
+		};
+
+		(axis(dx, dy), axis(dy, dx))
+	}
+
+	fn emit(control: PadControl, value: (i32, i32), events: &mut Vec<PadEvent>) {
+		match control {
+			PadControl::Stick { stick, .. } => {
+				let (x, y) = stick.axes();
+				events.push(PadEvent::Axis(x, value.0));
+				events.push(PadEvent::Axis(y, value.1));
+			}
+			PadControl::DPad { .. } => {
+				events.push(PadEvent::Hat(Hat::X0, value.0));
+				events.push(PadEvent::Hat(Hat::Y0, value.1));
+			}
+			PadControl::Button(button) => events.push(PadEvent::Button(button, value.0 != 0)),
+		}
+	}
+
+	pub fn process(&mut self, frame: &[Contact], events: &mut Vec<PadEvent>) {
+		let position = |a: &Contact| {
+			let (x, y) = a.screen_pos();
+			(x as i32, y as i32)
+		};
+
+		// Lifted fingers.
+		for (region, state) in self.regions.iter().zip(self.states.iter_mut()) {
+			if let Some(owner) = state.owner
+				&& !frame.iter().any(|a| a.tracking_id == owner)
+			{
+				*state = ControlState::default();
+				Self::emit(region.control, (0, 0), events);
+			}
+		}
+
+		for contact in frame.iter() {
+			let (x, y) = position(contact);
+			let index = match self
+				.states
+				.iter()
+				.position(|a| a.owner == Some(contact.tracking_id))
+			{
+				Some(a) => a,
+				None => {
+					// New finger, the first free control under it.
+					let Some(index) =
+						self.regions
+							.iter()
+							.zip(self.states.iter())
+							.position(|(a, state)| {
+								state.owner.is_none() && a.region.contains(x as u16, y as u16)
+							})
+					else {
+						continue;
+					};
+					self.states[index] = ControlState {
+						owner: Some(contact.tracking_id),
+						origin: (x, y),
+						value: (0, 0),
+					};
+					if let PadControl::Button(_) = self.regions[index].control {
+						// Pressed on contact, see `emit`.
+						self.states[index].value = (1, 0);
+						Self::emit(self.regions[index].control, (1, 0), events);
+					}
+					index
+				}
+			};
+
+			let state = &mut self.states[index];
+			let control = self.regions[index].control;
+			let value = match control {
+				PadControl::Stick {
+					dead_zone, radius, ..
+				} => Self::stick_value(x - state.origin.0, y - state.origin.1, dead_zone, radius),
+				PadControl::DPad { dead_zone } => {
+					let (cx, cy) = self.regions[index].region.center();
+					Self::dpad_value(x - cx as i32, y - cy as i32, dead_zone)
+				}
+				PadControl::Button(_) => state.value,
+			};
+			if value != state.value {
+				state.value = value;
+				Self::emit(control, value, events);
+			}
+		}
+	}
+
+	/// Releases every control.
+	#[inline]
+	pub fn cancel(&mut self, events: &mut Vec<PadEvent>) {
+		self.process(&[], events);
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_gamepad() {
+	use crate::config::NEEDS_COORDINATE_INVERSION;
+	use crate::config::SENSOR_MAX_X;
+	use crate::config::SENSOR_MAX_Y;
+
+	static REGIONS: &[PadRegion] = &[
+		PadRegion {
+			region: Region::Rect {
+				x: 0,
+				y: 0,
+				width: 400,
+				height: 400,
+			},
+			control: PadControl::Stick {
+				stick: Stick::Left,
+				dead_zone: 10,
+				radius: 110,
+			},
+		},
+		PadRegion {
+			region: Region::Circle {
+				x: 600,
+				y: 200,
+				radius: 50,
+			},
+			control: PadControl::Button(GamePad::South),
+		},
+	];
+	// Raw contact at a screen position.
+	let contact = |tracking_id, x: u16, y: u16| {
+		let (x, y) = if NEEDS_COORDINATE_INVERSION {
+			(SENSOR_MAX_X - y, x)
+		} else {
+			(x, SENSOR_MAX_Y - y)
+		};
+		Contact::new(tracking_id, x, y)
+	};
+	let mut pad = GamepadEmulator::new(REGIONS);
+	let mut events = Vec::new();
+
+	// Stick and button at the same time.
+	pad.process(&[contact(1, 200, 200), contact(2, 600, 200)], &mut events);
+	assert_eq!(events, [PadEvent::Button(GamePad::South, true)]);
+	events.clear();
+
+	// Within the dead zone, then full tilt to the right even outside the region.
+	pad.process(&[contact(1, 205, 200), contact(2, 600, 200)], &mut events);
+	assert!(events.is_empty());
+	pad.process(&[contact(1, 500, 200), contact(2, 600, 200)], &mut events);
+	assert_eq!(
+		events,
+		[
+			PadEvent::Axis(Position::X, AXIS_MAX),
+			PadEvent::Axis(Position::Y, 0)
+		]
+	);
+	events.clear();
+
+	pad.process(&[contact(2, 600, 200)], &mut events);
+	assert_eq!(
+		events,
+		[
+			PadEvent::Axis(Position::X, 0),
+			PadEvent::Axis(Position::Y, 0)
+		]
+	);
+	events.clear();
+	pad.cancel(&mut events);
+	assert_eq!(events, [PadEvent::Button(GamePad::South, false)]);
+
+	assert_eq!(GamepadEmulator::dpad_value(-50, 5, 10), (-1, 0));
+	assert_eq!(GamepadEmulator::dpad_value(40, 40, 10), (1, 1));
+	assert_eq!(GamepadEmulator::dpad_value(5, -5, 10), (0, 0));
+}
@@ -0,0 +1,376 @@
+use crate::action::Action;
+use crate::config::EDGE_SWIPE_BAND;
+use crate::config::GESTURE_BINDINGS;
+use crate::config::HOLD_DURATION;
+use crate::config::HOLD_TOLERANCE;
+use crate::config::PINCH_SCALE;
+use crate::config::ROTATE_MIN_ANGLE;
+use crate::config::SWIPE_MIN_DISTANCE;
+use crate::config::TEMPLATE_BINDINGS;
+use crate::config::TEMPLATE_DIR;
+use crate::config::TEMPLATE_MAX_ROTATION;
+use crate::config::TEMPLATE_MIN_SCORE;
+use crate::gesture::template::StrokeRecorder;
+use crate::gesture::template::TemplateRecognizer;
+use crate::model::Contact;
+use crate::model::SCREEN_MAX;
+use log::info;
+use log::trace;
+use std::f32::consts::PI;
+use std::path::Path;
+use std::time::Instant;
+
+pub mod template;
+
+// Directions and edges are in the display orientation.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+	Up,
+	Down,
+	Left,
+	Right,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+	Left,
+	Top,
+	Right,
+	Bottom,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+	Swipe {
+		fingers: usize,
+		direction: Direction,
+	},
+	// Single finger swipe from the bezel towards the centre.
+	EdgeSwipe(Edge),
+	PinchIn {
+		fingers: usize,
+	},
+	PinchOut {
+		fingers: usize,
+	},
+	// Two fingers.
+	Rotate {
+		clockwise: bool,
+	},
+	Hold {
+		fingers: usize,
+	},
+}
+
+/// Contacts of a session in the display orientation, with a fixed set of tracking ids.
+#[derive(Debug, Clone)]
+struct Shape {
+	ids: Vec<u16>,
+	points: Vec<(f32, f32)>,
+	center: (f32, f32),
+	// Mean distance from the centre.
+	spread: f32,
+}
+
+impl Shape {
+	fn new(frame: &[Contact]) -> Self {
+		let mut contacts: Vec<&Contact> = frame.iter().collect();
+		contacts.sort_by_key(|a| a.tracking_id);
+
+		let points: Vec<(f32, f32)> = contacts
+			.iter()
+			.map(|a| {
+				let (x, y) = a.screen_pos();
+				(x as f32, y as f32)
+			})
+			.collect();
+		let n = points.len().max(1) as f32;
+		let center = points
+			.iter()
+			.fold((0.0, 0.0), |(cx, cy), (x, y)| (cx + x / n, cy + y / n));
+		let spread = points
+			.iter()
+			.map(|(x, y)| (x - center.0).hypot(y - center.1))
+			.sum::<f32>()
+			/ n;
+
+		Self {
+			ids: contacts.iter().map(|a| a.tracking_id).collect(),
+			points,
+			center,
+			spread,
+		}
+	}
+
+	/// Angle of the line between the first two contacts.
+	#[inline]
+	fn angle(&self) -> f32 {
+		let ((x0, y0), (x1, y1)) = (self.points[0], self.points[1]);
+		(y1 - y0).atan2(x1 - x0)
+	}
+}
+
+struct Session {
+	// Reset every time the set of contacts changes.
+	base: Shape,
+	base_time: Instant,
+	max_move: f32,
+
+	is_fired: bool,
+}
+
+/// Recognises swipes, edge swipes, pinch, rotate and hold gestures and looks up their bindings.
+///
+/// At most one bound gesture fires per session (first contact down until all are lifted),
+/// after that the contacts are captured and must not reach the backends.
+/// Templates are matched once all contacts are lifted, if no other gesture fired.
+pub struct GestureEngine {
+	bindings: &'static [(Gesture, Action)],
+	session: Option<Session>,
+
+	templates: TemplateRecognizer,
+	template_bindings: &'static [(&'static str, Action)],
+	recorder: StrokeRecorder,
+}
+
+impl GestureEngine {
+	pub fn from_config() -> Self {
+		let templates = if TEMPLATE_BINDINGS.is_empty() {
+			Vec::new()
+		} else {
+			TemplateRecognizer::load_dir(Path::new(TEMPLATE_DIR))
+		};
+
+		Self::new(
+			GESTURE_BINDINGS,
+			TemplateRecognizer::new(templates, TEMPLATE_MIN_SCORE, TEMPLATE_MAX_ROTATION),
+			TEMPLATE_BINDINGS,
+		)
+	}
+
+	pub const fn new(
+		bindings: &'static [(Gesture, Action)],
+		templates: TemplateRecognizer,
+		template_bindings: &'static [(&'static str, Action)],
+	) -> Self {
+		Self {
+			bindings,
+			session: None,
+
+			templates,
+			template_bindings,
+			recorder: StrokeRecorder::new(),
+		}
+	}
+
+	#[inline]
+	pub fn is_captured(&self) -> bool {
+		self.session.as_ref().is_some_and(|a| a.is_fired)
+	}
+
+	fn edge_of((x, y): (f32, f32)) -> Option<Edge> {
+		let band = EDGE_SWIPE_BAND as f32;
+		let (max_x, max_y) = (SCREEN_MAX.0 as f32, SCREEN_MAX.1 as f32);
+
+		if x <= band {
+			Some(Edge::Left)
+		} else if y <= band {
+			Some(Edge::Top)
+		} else if x >= max_x - band {
+			Some(Edge::Right)
+		} else if y >= max_y - band {
+			Some(Edge::Bottom)
+		} else {
+			None
+		}
+	}
+
+	/// Every gesture the current shape qualifies for, most specific first.
+	fn recognize(session: &Session, shape: &Shape, now: Instant) -> Vec<Gesture> {
+		let base = &session.base;
+		let fingers = shape.ids.len();
+		let mut result = Vec::with_capacity(2);
+
+		let (dx, dy) = (
+			shape.center.0 - base.center.0,
+			shape.center.1 - base.center.1,
+		);
+		let distance = dx.hypot(dy);
+		let min_distance = SWIPE_MIN_DISTANCE as f32;
+
+		if fingers == 1
+			&& let Some(edge) = Self::edge_of(base.center)
+		{
+			let inward = match edge {
+				Edge::Left => dx,
+				Edge::Top => dy,
+				Edge::Right => -dx,
+				Edge::Bottom => -dy,
+			};
+			if inward >= min_distance {
+				result.push(Gesture::EdgeSwipe(edge));
+			}
+		}
+
+		if fingers >= 2 && base.spread > 0.0 {
+			let scale = shape.spread / base.spread;
+			if scale >= PINCH_SCALE {
+				result.push(Gesture::PinchOut { fingers });
+			} else if scale <= 1.0 / PINCH_SCALE {
+				result.push(Gesture::PinchIn { fingers });
+			}
+		}
+
+		if fingers == 2 {
+			let mut angle = shape.angle() - base.angle();
+			if angle > PI {
+				angle -= 2.0 * PI;
+			} else if angle < -PI {
+				angle += 2.0 * PI;
+			}
+			// y points down, a positive angle is clockwise.
+			if angle.abs() >= ROTATE_MIN_ANGLE.to_radians() {
+				result.push(Gesture::Rotate {
+					clockwise: angle > 0.0,
+				});
+			}
+		}
+
+		if distance >= min_distance {
+			let direction = if dx.abs() >= dy.abs() {
+				if dx > 0.0 {
+					Direction::Right
+				} else {
+					Direction::Left
+				}
+			} else if dy > 0.0 {
+				Direction::Down
+			} else {
+				Direction::Up
+			};
+			result.push(Gesture::Swipe { fingers, direction });
+		}
+
+		if session.max_move <= HOLD_TOLERANCE as f32
+			&& now.saturating_duration_since(session.base_time) >= HOLD_DURATION
+		{
+			result.push(Gesture::Hold { fingers });
+		}
+
+		result
+	}
+
+	fn finish(&mut self) -> Option<&'static Action> {
+		let session = self.session.take()?;
+		let strokes = self.recorder.take();
+		if session.is_fired || self.templates.is_empty() {
+			return None;
+		}
+
+		let (name, score) = self.templates.recognize(&strokes)?;
+		trace!("#[gesture] template {:?}, score: {:.2}", name, score);
+		let (_, action) = self.template_bindings.iter().find(|(a, _)| *a == name)?;
+		info!("#[gesture] template {:?}", name);
+
+		Some(action)
+	}
+
+	/// Returns the action of a gesture that just fired.
+	pub fn process(&mut self, frame: &[Contact], now: Instant) -> Option<&'static Action> {
+		if frame.is_empty() {
+			return self.finish();
+		}
+		if self.bindings.is_empty() && self.templates.is_empty() {
+			return None;
+		}
+		if !self.templates.is_empty() {
+			self.recorder.push(frame);
+		}
+
+		let shape = Shape::new(frame);
+		let session = self.session.get_or_insert_with(|| Session {
+			base: shape.clone(),
+			base_time: now,
+			max_move: 0.0,
+
+			is_fired: false,
+		});
+		if session.is_fired {
+			return None;
+		}
+		if session.base.ids != shape.ids {
+			trace!("#[gesture] fingers: {}", shape.ids.len());
+			session.base = shape;
+			session.base_time = now;
+			session.max_move = 0.0;
+
+			return None;
+		}
+		session.max_move = session.max_move.max(
+			(shape.center.0 - session.base.center.0).hypot(shape.center.1 - session.base.center.1),
+		);
+
+		let bindings = self.bindings;
+		for gesture in Self::recognize(session, &shape, now) {
+			if let Some((_, action)) = bindings.iter().find(|(a, _)| *a == gesture) {
+				info!("#[gesture] {:?}", gesture);
+				session.is_fired = true;
+
+				return Some(action);
+			}
+		}
+
+		None
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_gesture() {
+	use crate::config::NEEDS_COORDINATE_INVERSION;
+	use std::time::Duration;
+
+	static BINDINGS: &[(Gesture, Action)] = &[
+		(
+			Gesture::Swipe {
+				fingers: 3,
+				direction: Direction::Right,
+			},
+			Action::Command("true"),
+		),
+		(Gesture::PinchOut { fingers: 2 }, Action::Command("true")),
+	];
+	let mut engine = GestureEngine::new(BINDINGS, TemplateRecognizer::default(), &[]);
+	let mut now = Instant::now();
+
+	// Raw coordinates of a point moved by `d` to the right of the screen.
+	let contact = |tracking_id, i: u16, d: u16| {
+		let (x, y) = (300 + i * 40, 300 + i * 40);
+		let (x, y) = if NEEDS_COORDINATE_INVERSION {
+			(x, y + d)
+		} else {
+			(x + d, y)
+		};
+
+		Contact::new(tracking_id, x, y)
+	};
+
+	let frame = [contact(0, 0, 0), contact(1, 1, 0), contact(2, 2, 0)];
+	assert!(engine.process(&frame, now).is_none());
+	for d in [50, 100, 200] {
+		now += Duration::from_millis(20);
+		let frame = [contact(0, 0, d), contact(1, 1, d), contact(2, 2, d)];
+		if d < 200 {
+			assert!(engine.process(&frame, now).is_none());
+		} else {
+			assert!(engine.process(&frame, now).is_some());
+		}
+	}
+	assert!(engine.is_captured());
+
+	assert!(engine.process(&[], now).is_none());
+	assert!(!engine.is_captured());
+}
@@ -0,0 +1,432 @@
+use crate::model::Contact;
+use log::error;
+use log::info;
+use log::warn;
+use std::fs::create_dir_all;
+use std::fs::read_dir;
+use std::fs::read_to_string;
+use std::fs::write;
+use std::path::Path;
+
+// Points per normalised unistroke and the side of the reference square.
+const RESAMPLE_POINTS: usize = 64;
+const SQUARE_SIZE: f32 = 250.0;
+// Templates with more strokes are not permuted, the order of fingers is taken as is.
+const MAX_PERMUTED_STROKES: usize = 4;
+const FILE_HEADER: &str = "# utouch_rs template v1";
+
+pub type Point = (f32, f32);
+pub type Stroke = Vec<Point>;
+
+/// Collects one stroke per finger of a session, in the display orientation.
+#[derive(Debug, Default)]
+pub struct StrokeRecorder {
+	strokes: Vec<(u16, Stroke)>,
+}
+
+impl StrokeRecorder {
+	#[inline]
+	pub const fn new() -> Self {
+		Self {
+			strokes: Vec::new(),
+		}
+	}
+
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.strokes.is_empty()
+	}
+
+	pub fn push(&mut self, frame: &[Contact]) {
+		for contact in frame.iter() {
+			let (x, y) = contact.screen_pos();
+			let point = (x as f32, y as f32);
+			match self
+				.strokes
+				.iter_mut()
+				.find(|(id, _)| *id == contact.tracking_id)
+			{
+				Some((_, stroke)) => {
+					if stroke.last() != Some(&point) {
+						stroke.push(point);
+					}
+				}
+				None => self.strokes.push((contact.tracking_id, vec![point])),
+			}
+		}
+	}
+
+	/// Strokes in order of appearance, the recorder is empty afterwards.
+	pub fn take(&mut self) -> Vec<Stroke> {
+		self.strokes.sort_by_key(|(id, _)| *id);
+		self.strokes.drain(..).map(|(_, a)| a).collect()
+	}
+}
+
+fn path_length(points: &[Point]) -> f32 {
+	points
+		.windows(2)
+		.map(|a| (a[1].0 - a[0].0).hypot(a[1].1 - a[0].1))
+		.sum()
+}
+
+fn resample(points: &[Point], n: usize) -> Stroke {
+	let mut result = Vec::with_capacity(n);
+	let Some(first) = points.first() else {
+		return result;
+	};
+	result.push(*first);
+
+	let interval = path_length(points) / (n - 1) as f32;
+	if interval > 0.0 {
+		let mut distance = 0.0;
+		let mut prev = *first;
+		let mut i = 1;
+		while i < points.len() {
+			let point = points[i];
+			let d = (point.0 - prev.0).hypot(point.1 - prev.1);
+			if distance + d >= interval && d > 0.0 {
+				let t = (interval - distance) / d;
+				prev = (
+					prev.0 + t * (point.0 - prev.0),
+					prev.1 + t * (point.1 - prev.1),
+				);
+				result.push(prev);
+				distance = 0.0;
+			} else {
+				distance += d;
+				prev = point;
+				i += 1;
+			}
+		}
+	}
+	// Rounding may lose the last point.
+	while result.len() < n {
+		result.push(*points.last().unwrap());
+	}
+	result.truncate(n);
+
+	result
+}
+
+fn centroid(points: &[Point]) -> Point {
+	let n = points.len().max(1) as f32;
+	points
+		.iter()
+		.fold((0.0, 0.0), |(cx, cy), (x, y)| (cx + x / n, cy + y / n))
+}
+
+fn rotate(points: &[Point], angle: f32) -> Stroke {
+	let (c, (sin, cos)) = (centroid(points), angle.sin_cos());
+	points
+		.iter()
+		.map(|(x, y)| {
+			let (dx, dy) = (x - c.0, y - c.1);
+			(dx * cos - dy * sin + c.0, dx * sin + dy * cos + c.1)
+		})
+		.collect()
+}
+
+/// Uniform scale to `SQUARE_SIZE` and translation to the origin,
+/// so that straight lines keep their direction.
+fn normalize(points: &[Point]) -> Stroke {
+	let (mut min, mut max) = ((f32::MAX, f32::MAX), (f32::MIN, f32::MIN));
+	for (x, y) in points.iter() {
+		min = (min.0.min(*x), min.1.min(*y));
+		max = (max.0.max(*x), max.1.max(*y));
+	}
+	let size = (max.0 - min.0).max(max.1 - min.1).max(1.0);
+	let scale = SQUARE_SIZE / size;
+
+	let scaled: Stroke = points.iter().map(|(x, y)| (x * scale, y * scale)).collect();
+	let c = centroid(&scaled);
+	scaled.iter().map(|(x, y)| (x - c.0, y - c.1)).collect()
+}
+
+fn path_distance(a: &[Point], b: &[Point]) -> f32 {
+	let n = a.len().min(b.len()).max(1) as f32;
+	a.iter()
+		.zip(b.iter())
+		.map(|(a, b)| (a.0 - b.0).hypot(a.1 - b.1))
+		.sum::<f32>()
+		/ n
+}
+
+/// Smallest distance with the candidate rotated within `max_angle`, golden section search.
+fn distance_at_best_angle(candidate: &[Point], template: &[Point], max_angle: f32) -> f32 {
+	const PHI: f32 = 0.618_034;
+	const PRECISION: f32 = 2.0 * std::f32::consts::PI / 180.0;
+
+	let (mut a, mut b) = (-max_angle, max_angle);
+	let mut x1 = PHI * a + (1.0 - PHI) * b;
+	let mut f1 = path_distance(&rotate(candidate, x1), template);
+	let mut x2 = (1.0 - PHI) * a + PHI * b;
+	let mut f2 = path_distance(&rotate(candidate, x2), template);
+	while (b - a).abs() > PRECISION {
+		if f1 < f2 {
+			b = x2;
+			x2 = x1;
+			f2 = f1;
+			x1 = PHI * a + (1.0 - PHI) * b;
+			f1 = path_distance(&rotate(candidate, x1), template);
+		} else {
+			a = x1;
+			x1 = x2;
+			f1 = f2;
+			x2 = (1.0 - PHI) * a + PHI * b;
+			f2 = path_distance(&rotate(candidate, x2), template);
+		}
+	}
+
+	f1.min(f2)
+}
+
+/// Strokes joined into one unistroke, each resampled separately so that every finger
+/// has the same weight.
+fn unistroke(strokes: &[&Stroke]) -> Stroke {
+	let per_stroke = (RESAMPLE_POINTS / strokes.len().max(1)).max(2);
+	let joined: Stroke = strokes
+		.iter()
+		.flat_map(|a| resample(a, per_stroke))
+		.collect();
+
+	normalize(&joined)
+}
+
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+	if n == 0 {
+		return vec![Vec::new()];
+	}
+
+	let mut result = Vec::new();
+	for rest in permutations(n - 1) {
+		for i in 0..=rest.len() {
+			let mut a = rest.clone();
+			a.insert(i, n - 1);
+			result.push(a);
+		}
+	}
+
+	result
+}
+
+/// Named multistroke template, one stroke per finger.
+#[derive(Debug, Clone)]
+pub struct Template {
+	pub name: String,
+	strokes: Vec<Stroke>,
+	// Normalised unistrokes for every order of the strokes ($N).
+	unistrokes: Vec<Stroke>,
+}
+
+impl Template {
+	pub fn new(name: String, strokes: Vec<Stroke>) -> Self {
+		let unistrokes = if strokes.len() <= MAX_PERMUTED_STROKES {
+			permutations(strokes.len())
+				.iter()
+				.map(|order| unistroke(&order.iter().map(|i| &strokes[*i]).collect::<Vec<_>>()))
+				.collect()
+		} else {
+			vec![unistroke(&strokes.iter().collect::<Vec<_>>())]
+		};
+
+		Self {
+			name,
+			strokes,
+			unistrokes,
+		}
+	}
+
+	#[inline]
+	pub fn strokes(&self) -> &[Stroke] {
+		&self.strokes
+	}
+
+	pub fn to_text(&self) -> String {
+		let mut result = String::from(FILE_HEADER);
+		for stroke in self.strokes.iter() {
+			result.push('\n');
+			let points: Vec<String> = stroke
+				.iter()
+				.map(|(x, y)| format!("{},{}", x.round(), y.round()))
+				.collect();
+			result.push_str(&points.join(" "));
+		}
+		result.push('\n');
+
+		result
+	}
+
+	pub fn from_text(name: String, text: &str) -> anyhow::Result<Self> {
+		let mut lines = text.lines();
+		if lines.next() != Some(FILE_HEADER) {
+			anyhow::bail!("unknown template header");
+		}
+
+		let mut strokes = Vec::new();
+		for line in lines.filter(|a| !a.trim().is_empty()) {
+			let mut stroke = Stroke::new();
+			for point in line.split_whitespace() {
+				let Some((x, y)) = point.split_once(',') else {
+					anyhow::bail!("invalid point: {:?}", point);
+				};
+				stroke.push((x.parse()?, y.parse()?));
+			}
+			strokes.push(stroke);
+		}
+		if strokes.is_empty() {
+			anyhow::bail!("empty template");
+		}
+
+		Ok(Self::new(name, strokes))
+	}
+
+	pub fn save(&self, dir: &Path) -> anyhow::Result<()> {
+		create_dir_all(dir)?;
+		write(dir.join(format!("{}.stroke", self.name)), self.to_text())?;
+
+		Ok(())
+	}
+}
+
+/// Matches strokes against templates in the style of the $1/$N recognisers.
+#[derive(Debug, Default)]
+pub struct TemplateRecognizer {
+	templates: Vec<Template>,
+	min_score: f32,
+	max_angle: f32,
+}
+
+impl TemplateRecognizer {
+	pub const fn new(templates: Vec<Template>, min_score: f32, max_angle: f32) -> Self {
+		Self {
+			templates,
+			min_score,
+			max_angle: max_angle.to_radians(),
+		}
+	}
+
+	/// Every `*.stroke` file in `dir`, broken files are skipped.
+	pub fn load_dir(dir: &Path) -> Vec<Template> {
+		let mut result = Vec::new();
+		let entries = match read_dir(dir) {
+			Ok(a) => a,
+			Err(e) => {
+				warn!("#[template] read dir {:?}, err: {:?}", dir, e);
+				return result;
+			}
+		};
+
+		for path in entries.flatten().map(|a| a.path()) {
+			if path.extension().is_none_or(|a| a != "stroke") {
+				continue;
+			}
+			let Some(name) = path.file_stem().and_then(|a| a.to_str()) else {
+				continue;
+			};
+
+			match read_to_string(&path)
+				.map_err(anyhow::Error::from)
+				.and_then(|text| Template::from_text(name.to_string(), &text))
+			{
+				Ok(a) => {
+					info!("#[template] {:?}, strokes: {}", a.name, a.strokes.len());
+					result.push(a);
+				}
+				Err(e) => error!("#[template] load {:?}, err: {:?}", path, e),
+			}
+		}
+
+		result
+	}
+
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.templates.is_empty()
+	}
+
+	/// Best template with the same number of strokes and its score in `0.0..=1.0`.
+	pub fn recognize(&self, strokes: &[Stroke]) -> Option<(&str, f32)> {
+		if strokes.is_empty() || strokes.iter().all(|a| path_length(a) == 0.0) {
+			return None;
+		}
+		let candidate = unistroke(&strokes.iter().collect::<Vec<_>>());
+		let half_diagonal = 0.5 * (2.0 * SQUARE_SIZE * SQUARE_SIZE).sqrt();
+
+		let mut best: Option<(&str, f32)> = None;
+		for template in self
+			.templates
+			.iter()
+			.filter(|a| a.strokes.len() == strokes.len())
+		{
+			for unistroke in template.unistrokes.iter() {
+				let distance = distance_at_best_angle(&candidate, unistroke, self.max_angle);
+				let score = 1.0 - distance / half_diagonal;
+				if best.is_none_or(|(_, a)| score > a) {
+					best = Some((template.name.as_str(), score));
+				}
+			}
+		}
+
+		best.filter(|(_, a)| *a >= self.min_score)
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_template() {
+	let line = |from: Point, to: Point| -> Stroke {
+		(0..=20)
+			.map(|i| {
+				let t = i as f32 / 20.0;
+				(from.0 + t * (to.0 - from.0), from.1 + t * (to.1 - from.1))
+			})
+			.collect()
+	};
+	let corner = |x: f32, y: f32, size: f32| -> Stroke {
+		let mut a = line((x, y), (x, y + size));
+		a.extend(line((x, y + size), (x + size, y + size)));
+		a
+	};
+
+	let templates = vec![
+		Template::new("L".to_string(), vec![corner(0.0, 0.0, 100.0)]),
+		Template::new("right".to_string(), vec![line((0.0, 0.0), (100.0, 0.0))]),
+		Template::new(
+			"two".to_string(),
+			vec![
+				line((0.0, 0.0), (0.0, 100.0)),
+				line((50.0, 0.0), (50.0, 100.0)),
+			],
+		),
+	];
+	let text = templates[0].to_text();
+	let loaded = Template::from_text("L".to_string(), &text).unwrap();
+	assert_eq!(loaded.strokes().len(), 1);
+
+	let recognizer = TemplateRecognizer::new(templates, 0.8, 15.0);
+
+	// Bigger and shifted.
+	let (name, _) = recognizer
+		.recognize(&[corner(300.0, 200.0, 400.0)])
+		.unwrap();
+	assert_eq!(name, "L");
+	let (name, _) = recognizer
+		.recognize(&[line((500.0, 500.0), (900.0, 520.0))])
+		.unwrap();
+	assert_eq!(name, "right");
+	// The opposite direction does not match.
+	assert!(
+		recognizer
+			.recognize(&[line((900.0, 500.0), (500.0, 500.0))])
+			.is_none_or(|(a, _)| a != "right")
+	);
+	// Finger order does not matter.
+	let (name, _) = recognizer
+		.recognize(&[
+			line((250.0, 0.0), (250.0, 500.0)),
+			line((0.0, 0.0), (0.0, 500.0)),
+		])
+		.unwrap();
+	assert_eq!(name, "two");
+}
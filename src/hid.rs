@@ -0,0 +1,176 @@
+use crate::config::SENSOR_SIZE_MM;
+use crate::model::Contact;
+use crate::model::SCREEN_MAX;
+
+// Contacts per input report, every report carries all of them ("parallel mode").
+pub const HID_MAX_CONTACTS: usize = 10;
+pub const REPORT_ID_TOUCH: u8 = 1;
+pub const REPORT_ID_MAX_COUNT: u8 = 2;
+
+// Tip switch with padding, contact id, x and y.
+const CONTACT_SIZE: usize = 1 + 1 + 2 + 2;
+// Report id, contacts, contact count.
+pub const TOUCH_REPORT_SIZE: usize = 1 + CONTACT_SIZE * HID_MAX_CONTACTS + 1;
+
+/// Multitouch digitizer report descriptor in the Windows touch screen layout, the kernel's
+/// hid-multitouch driver handles it without quirks.
+pub fn report_descriptor() -> Vec<u8> {
+	let [max_x_lo, max_x_hi] = SCREEN_MAX.0.to_le_bytes();
+	let [max_y_lo, max_y_hi] = SCREEN_MAX.1.to_le_bytes();
+	// In 0.1 mm, see the unit below.
+	let [phys_x_lo, phys_x_hi] = (SENSOR_SIZE_MM.0 * 10).to_le_bytes();
+	let [phys_y_lo, phys_y_hi] = (SENSOR_SIZE_MM.1 * 10).to_le_bytes();
+
+	#[rustfmt::skip]
+	let mut result = vec![
+		0x05, 0x0D, // Usage Page (Digitizer)
+		0x09, 0x04, // Usage (Touch Screen)
+		0xA1, 0x01, // Collection (Application)
+		0x85, REPORT_ID_TOUCH, // Report ID
+	];
+	for _ in 0..HID_MAX_CONTACTS {
+		result.extend_from_slice(&[
+			0x05, 0x0D, // Usage Page (Digitizer)
+			0x09, 0x22, // Usage (Finger)
+			0xA1, 0x02, // Collection (Logical)
+			0x09, 0x42, // Usage (Tip Switch)
+			0x15, 0x00, // Logical Minimum (0)
+			0x25, 0x01, // Logical Maximum (1)
+			0x75, 0x01, // Report Size (1)
+			0x95, 0x01, // Report Count (1)
+			0x81, 0x02, // Input (Data, Var, Abs)
+			0x95, 0x07, // Report Count (7)
+			0x81, 0x03, // Input (Const), padding
+			0x09, 0x51, // Usage (Contact Identifier)
+			0x26, 0xFF, 0x00, // Logical Maximum (255)
+			0x75, 0x08, // Report Size (8)
+			0x95, 0x01, // Report Count (1)
+			0x81, 0x02, // Input (Data, Var, Abs)
+			0x05, 0x01, // Usage Page (Generic Desktop)
+			0x55, 0x0E, // Unit Exponent (-2)
+			0x65, 0x11, // Unit (SI Linear, cm)
+			0x75, 0x10, // Report Size (16)
+			0x09, 0x30, // Usage (X)
+			0x26, max_x_lo, max_x_hi, // Logical Maximum
+			0x46, phys_x_lo, phys_x_hi, // Physical Maximum
+			0x81, 0x02, // Input (Data, Var, Abs)
+			0x09, 0x31, // Usage (Y)
+			0x26, max_y_lo, max_y_hi, // Logical Maximum
+			0x46, phys_y_lo, phys_y_hi, // Physical Maximum
+			0x81, 0x02, // Input (Data, Var, Abs)
+			0x45, 0x00, // Physical Maximum (0)
+			0x55, 0x00, // Unit Exponent (0)
+			0x65, 0x00, // Unit (None)
+			0xC0, // End Collection
+		]);
+	}
+	#[rustfmt::skip]
+	result.extend_from_slice(&[
+		0x05, 0x0D, // Usage Page (Digitizer)
+		0x09, 0x54, // Usage (Contact Count)
+		0x25, 0x7F, // Logical Maximum (127)
+		0x75, 0x08, // Report Size (8)
+		0x95, 0x01, // Report Count (1)
+		0x81, 0x02, // Input (Data, Var, Abs)
+		0x85, REPORT_ID_MAX_COUNT, // Report ID
+		0x09, 0x55, // Usage (Contact Count Maximum)
+		0x25, HID_MAX_CONTACTS as u8, // Logical Maximum
+		0xB1, 0x02, // Feature (Data, Var, Abs)
+		0xC0, // End Collection
+	]);
+
+	result
+}
+
+/// Feature report with the contact count maximum.
+#[inline]
+pub const fn max_count_report() -> [u8; 2] {
+	[REPORT_ID_MAX_COUNT, HID_MAX_CONTACTS as u8]
+}
+
+/// Contacts to touch input reports, lifted contacts are reported once without the tip switch.
+#[derive(Debug, Default)]
+pub struct HidEncoder {
+	// (tracking id, x, y) of the previous report.
+	last: Vec<(u16, u16, u16)>,
+	report: Vec<u8>,
+}
+
+impl HidEncoder {
+	pub fn new() -> Self {
+		Self {
+			last: Vec::with_capacity(HID_MAX_CONTACTS),
+			report: Vec::with_capacity(TOUCH_REPORT_SIZE),
+		}
+	}
+
+	/// `None` once every contact was reported lifted.
+	pub fn encode(&mut self, frame: &[Contact]) -> Option<&[u8]> {
+		let mut contacts: Vec<(u16, u16, u16, bool)> = frame
+			.iter()
+			.take(HID_MAX_CONTACTS)
+			.map(|a| {
+				let (x, y) = a.screen_pos();
+				(a.tracking_id, x, y, true)
+			})
+			.collect();
+		for (id, x, y) in self.last.iter() {
+			if contacts.len() < HID_MAX_CONTACTS && !contacts.iter().any(|a| a.0 == *id) {
+				contacts.push((*id, *x, *y, false));
+			}
+		}
+		if contacts.is_empty() {
+			return None;
+		}
+
+		self.report.clear();
+		self.report.push(REPORT_ID_TOUCH);
+		for n in 0..HID_MAX_CONTACTS {
+			let (id, x, y, is_tip) = contacts.get(n).copied().unwrap_or_default();
+			self.report.push(is_tip as u8);
+			self.report.push(id as u8);
+			self.report.extend_from_slice(&x.to_le_bytes());
+			self.report.extend_from_slice(&y.to_le_bytes());
+		}
+		self.report.push(contacts.len() as u8);
+
+		self.last.clear();
+		self.last.extend(
+			contacts
+				.iter()
+				.filter(|a| a.3)
+				.map(|(id, x, y, _)| (*id, *x, *y)),
+		);
+
+		Some(&self.report)
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_hid() {
+	let descriptor = report_descriptor();
+	// Every collection is closed.
+	let open = descriptor.windows(2).filter(|a| a[0] == 0xA1).count();
+	let close = descriptor.iter().filter(|a| **a == 0xC0).count();
+	assert_eq!(open, close);
+
+	let mut encoder = HidEncoder::new();
+	let report = encoder
+		.encode(&[Contact::new(3, 100, 200), Contact::new(4, 100, 200)])
+		.unwrap();
+	assert_eq!(report.len(), TOUCH_REPORT_SIZE);
+	assert_eq!(report[0], REPORT_ID_TOUCH);
+	assert_eq!(report[1..3], [1, 3]);
+	assert_eq!(report[1 + CONTACT_SIZE..3 + CONTACT_SIZE], [1, 4]);
+	assert_eq!(report[TOUCH_REPORT_SIZE - 1], 2);
+
+	// The second contact lifted.
+	let report = encoder.encode(&[Contact::new(3, 100, 200)]).unwrap();
+	assert_eq!(report[1 + CONTACT_SIZE..3 + CONTACT_SIZE], [0, 4]);
+	assert_eq!(report[TOUCH_REPORT_SIZE - 1], 2);
+
+	let report = encoder.encode(&[]).unwrap();
+	assert_eq!(report[1..3], [0, 3]);
+	assert!(encoder.encode(&[]).is_none());
+}
@@ -0,0 +1,155 @@
+use crate::config::GADGET_IDS;
+use crate::config::GADGET_NAME;
+use crate::config::GADGET_STRINGS;
+use crate::config::GADGET_UDC;
+use crate::hid::HidEncoder;
+use crate::hid::TOUCH_REPORT_SIZE;
+use crate::hid::report_descriptor;
+use crate::model::Contact;
+use log::info;
+use log::trace;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::fs::create_dir_all;
+use std::fs::read_dir;
+use std::fs::read_to_string;
+use std::fs::remove_file;
+use std::fs::write;
+use std::io;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use std::path::PathBuf;
+
+const CONFIGFS: &str = "/sys/kernel/config/usb_gadget";
+const FUNCTION: &str = "hid.usb0";
+const CONFIG: &str = "c.1";
+
+/// `/dev/hidgN` of a function from its `dev` attribute (`major:minor`).
+fn hidg_path(dev: &str) -> Option<PathBuf> {
+	let (_, minor) = dev.trim().split_once(':')?;
+	let minor: u32 = minor.parse().ok()?;
+
+	Some(PathBuf::from(format!("/dev/hidg{}", minor)))
+}
+
+fn ignore_not_found(result: io::Result<()>) -> io::Result<()> {
+	match result {
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+		a => a,
+	}
+}
+
+/// The board as a USB touchscreen of another computer: a HID function of the Linux USB gadget
+/// configfs, reports are written to `/dev/hidgN`.
+///
+/// Locally testable with `modprobe dummy_hcd`, the gadget then appears on this machine.
+pub struct HidGadget {
+	gadget: PathBuf,
+	file: File,
+	encoder: HidEncoder,
+}
+
+impl HidGadget {
+	pub fn new() -> anyhow::Result<Self> {
+		let gadget = Path::new(CONFIGFS).join(GADGET_NAME);
+		info!("#[gadget] configure {:?}", gadget);
+
+		// The descriptor can not change while bound.
+		let udc = gadget.join("UDC");
+		if udc.exists() && !read_to_string(&udc)?.trim().is_empty() {
+			write(&udc, "\n")?;
+		}
+		// Nor while the function is linked into a config (EBUSY), a previous run left the link.
+		let link = gadget.join("configs").join(CONFIG).join(FUNCTION);
+		ignore_not_found(remove_file(&link))?;
+
+		let (vendor, product) = GADGET_IDS;
+		let (manufacturer, product_name, serial) = GADGET_STRINGS;
+		create_dir_all(gadget.join("strings/0x409"))?;
+		write(gadget.join("idVendor"), format!("{:#06x}", vendor))?;
+		write(gadget.join("idProduct"), format!("{:#06x}", product))?;
+		write(gadget.join("bcdDevice"), "0x0100")?;
+		write(gadget.join("bcdUSB"), "0x0200")?;
+		write(gadget.join("strings/0x409/manufacturer"), manufacturer)?;
+		write(gadget.join("strings/0x409/product"), product_name)?;
+		write(gadget.join("strings/0x409/serialnumber"), serial)?;
+
+		let function = gadget.join("functions").join(FUNCTION);
+		create_dir_all(&function)?;
+		write(function.join("protocol"), "0")?;
+		write(function.join("subclass"), "0")?;
+		write(
+			function.join("report_length"),
+			TOUCH_REPORT_SIZE.to_string(),
+		)?;
+		write(function.join("report_desc"), report_descriptor())?;
+
+		let config = gadget.join("configs").join(CONFIG);
+		create_dir_all(config.join("strings/0x409"))?;
+		write(config.join("strings/0x409/configuration"), "touchscreen")?;
+		write(config.join("MaxPower"), "100")?;
+		symlink(&function, &link)?;
+
+		let udc_name = match GADGET_UDC {
+			Some(a) => a.to_string(),
+			None => read_dir("/sys/class/udc")?
+				.flatten()
+				.map(|a| a.file_name().to_string_lossy().into_owned())
+				.min()
+				.ok_or_else(|| anyhow::anyhow!("no USB device controller, modprobe dummy_hcd?"))?,
+		};
+		write(&udc, &udc_name)?;
+		info!("#[gadget] bound to {:?}", udc_name);
+
+		let dev = read_to_string(function.join("dev"))?;
+		let path = hidg_path(&dev).ok_or_else(|| anyhow::anyhow!("invalid dev: {:?}", dev))?;
+		info!("#[gadget] reports to {:?}", path);
+		// A host that does not poll must not block the decoder.
+		let file = OpenOptions::new()
+			.write(true)
+			.custom_flags(libc::O_NONBLOCK)
+			.open(path)?;
+
+		Ok(Self {
+			gadget,
+			file,
+			encoder: HidEncoder::new(),
+		})
+	}
+
+	pub fn push_frame(&mut self, frame: &[Contact]) -> anyhow::Result<()> {
+		let Some(report) = self.encoder.encode(frame) else {
+			return Ok(());
+		};
+
+		match self.file.write_all(report) {
+			Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+				trace!("#[gadget] host is not reading, report dropped");
+				Ok(())
+			}
+			// Not connected to a host.
+			Err(e) if e.raw_os_error() == Some(libc::ESHUTDOWN) => {
+				trace!("#[gadget] not connected, report dropped");
+				Ok(())
+			}
+			a => Ok(a?),
+		}
+	}
+}
+
+impl Drop for HidGadget {
+	fn drop(&mut self) {
+		let _e = write(self.gadget.join("UDC"), "\n");
+		let _e = remove_file(self.gadget.join("configs").join(CONFIG).join(FUNCTION));
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_hid_gadget() {
+	assert_eq!(hidg_path("239:0\n"), Some(PathBuf::from("/dev/hidg0")));
+	assert_eq!(hidg_path("239:3"), Some(PathBuf::from("/dev/hidg3")));
+	assert_eq!(hidg_path("invalid"), None);
+}
@@ -1,13 +1,60 @@
+use crate::action::ActionRunner;
+use crate::button::VirtualButtons;
+use crate::config::ANIMATION_INTERVAL;
 use crate::config::DEFAULT_I2C_BUS;
 use crate::config::DISPLAY_HEIGHT;
 use crate::config::DISPLAY_WIDTH;
+use crate::config::EVDEV_GRAB;
+use crate::config::EVDEV_PATH;
+use crate::config::GAMEPAD_REGIONS;
+use crate::config::HOVER;
 use crate::config::I2C_ADDR;
 use crate::config::I2C_NUM_BUS0;
 use crate::config::NEEDS_COORDINATE_INVERSION;
+use crate::config::NET_KEEPALIVE;
+use crate::config::NET_LINK_TIMEOUT;
+use crate::config::NET_LISTEN;
+use crate::config::NET_PEER;
+use crate::config::NET_RECONNECT_INTERVAL;
+use crate::config::NET_TRANSPORT;
 use crate::config::RPPAL_INT_PIN;
 use crate::config::RPPAL_RESPIN;
+use crate::config::SERIAL_BAUD;
+use crate::config::SERIAL_FRAMING;
+use crate::config::SERIAL_IDLE_TIMEOUT;
+use crate::config::SERIAL_PATH;
+use crate::config::SERIAL_RECONNECT_INTERVAL;
+use crate::config::TEMPLATE_DIR;
+use crate::gamepad::GamepadEmulator;
+use crate::gamepad::PadEvent;
+use crate::gesture::GestureEngine;
+use crate::gesture::template::StrokeRecorder;
+use crate::gesture::template::Template;
+use crate::hid_gadget::HidGadget;
 use crate::model::BuildReader;
+use crate::model::Contact;
 use crate::model::Reader;
+use crate::model::SCREEN_MAX;
+use crate::model::screen_delta;
+use crate::model::screen_point;
+use crate::mouse::MouseEmulator;
+use crate::mouse::MouseEvent;
+use crate::mouse::MouseMode;
+use crate::net::NetEvent;
+use crate::net::NetListener;
+use crate::net::NetPayload;
+use crate::net::NetSender;
+use crate::pen::PenDevice;
+use crate::pipeline::Pipeline;
+use crate::source::StreamEvent;
+use crate::source::capture;
+use crate::source::capture::Capture;
+use crate::source::capture::CaptureEvent;
+use crate::source::capture::CaptureWriter;
+use crate::source::evdev::EvdevSource;
+use crate::source::serial;
+use crate::touchpad::TouchpadDevice;
+use crate::uhid::UhidDevice;
 use enclose::enc;
 use log::error;
 use log::info;
@@ -16,21 +63,30 @@ use rand::Rng;
 use rand::rng;
 use rppal::gpio::Gpio;
 use rppal::i2c::I2c;
+use std::env::args;
 use std::env::set_var;
 use std::env::var_os;
 use std::ffi::OsStr;
 use std::ffi::OsString;
 use std::fs::read_dir;
+use std::io;
+use std::ops::ControlFlow;
 use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
+use std::path::PathBuf;
 use std::process::Command;
 use std::sync::Arc;
 use std::sync::Barrier;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::mpsc::channel;
 use std::thread::sleep;
 use std::thread::spawn;
 use std::time::Duration;
+use std::time::Instant;
 use tfc::Context;
+use tfc::Key;
+use tfc::KeyboardContext;
 use tfc::MouseButton;
 use tfc::MouseContext;
 use uinput::Device;
@@ -38,12 +94,30 @@ use uinput::event::Absolute::Multi;
 use uinput::event::Controller::Digi;
 use uinput::event::Event::Absolute;
 use uinput::event::Event::Controller;
-use uinput::event::absolute::Multi::{PositionX, PositionY, Slot, TrackingId};
+use uinput::event::absolute;
+use uinput::event::absolute::Multi::{Distance, PositionX, PositionY, Slot, TrackingId};
+use uinput::event::controller;
 use uinput::event::controller::Digi::Touch;
+use uinput::event::keyboard;
+use uinput::event::relative;
 
+mod action;
+mod button;
 mod config;
 mod core;
+mod gamepad;
+mod gesture;
+mod hid;
+mod hid_gadget;
 mod model;
+mod mouse;
+mod net;
+mod pen;
+mod pipeline;
+mod raw_uinput;
+mod source;
+mod touchpad;
+mod uhid;
 
 #[derive(Debug, Clone, Copy)]
 #[repr(u8)]
@@ -55,8 +129,35 @@ pub enum CTypeTransformCoordinates {
 #[derive(Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum CIdentDevice {
-	X11OrWayland(CTypeTransformCoordinates),
+	X11OrWayland(CTypeTransformCoordinates, MouseMode),
 	UInput,
+	// Relative mouse in `MouseMode::Touchpad`, absolute pointer otherwise (any Wayland
+	// compositor or the console, no X server needed).
+	UInputMouse(MouseMode),
+	// Multitouch touchpad for libinput.
+	Touchpad,
+	// Pen tablet driven by the primary contact.
+	Pen,
+	// HID multitouch digitizer through /dev/uhid.
+	Uhid,
+	// The same digitizer as a USB gadget for another computer.
+	HidGadget,
+	// Frames or raw bytes to another machine, see `net`.
+	Net(NetPayload),
+	Gamepad,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+pub enum CSource {
+	// The controller on the I2C bus, with the reset and interrupt pins.
+	I2c,
+	// Touchscreen with a kernel driver, see `source::evdev`.
+	Evdev,
+	// The controller bytes forwarded by a microcontroller over a serial port.
+	Serial,
+	// Raw bytes or contacts from another machine, see `net`.
+	Net,
 }
 
 pub enum InputDevice {
@@ -64,33 +165,48 @@ pub enum InputDevice {
 		context: Context,
 		transform: CTypeTransformCoordinates,
 
-		cx: u16,
-		cy: u16,
-
-		is_add_click: bool,
+		emulator: MouseEmulator,
+		events: Vec<MouseEvent>,
 	},
 	UInput {
 		device: Device,
 		a_slot: usize,
 
-		fingers: [Option<i8>; 12],
+		fingers: [Option<u16>; 12],
+	},
+	UInputMouse {
+		device: Device,
+
+		emulator: MouseEmulator,
+		events: Vec<MouseEvent>,
+		// Wheel units not yet emitted as whole notches.
+		wheel: (i32, i32),
+	},
+	Touchpad(TouchpadDevice),
+	Pen(PenDevice),
+	Uhid(UhidDevice),
+	HidGadget(HidGadget),
+	Net(NetSender),
+	Gamepad {
+		device: Device,
+
+		pad: GamepadEmulator,
+		events: Vec<PadEvent>,
 	},
 }
 
 impl InputDevice {
 	pub fn new(c_ident_device: CIdentDevice) -> anyhow::Result<Self> {
 		match c_ident_device {
-			CIdentDevice::X11OrWayland(transform) => {
+			CIdentDevice::X11OrWayland(transform, mode) => {
 				let context = Context::new()?;
 
 				Ok(Self::X11OrWayland {
 					context,
 					transform,
 
-					cx: 0,
-					cy: 0,
-
-					is_add_click: false,
+					emulator: MouseEmulator::new(mode),
+					events: Vec::with_capacity(8),
 				})
 			}
 			CIdentDevice::UInput => {
@@ -108,7 +224,11 @@ impl InputDevice {
 					//.min(0).max(12)
 					.event(Absolute(Multi(TrackingId)))?
 					.min(0)
-					.max(12)
+					.max(u16::MAX as _)
+					// 1 - hovering, see `Contact::is_hovering`.
+					.event(Absolute(Multi(Distance)))?
+					.min(0)
+					.max(1)
 					.event(Controller(Digi(Touch)))?
 					.create()?;
 
@@ -118,6 +238,90 @@ impl InputDevice {
 					a_slot: 0,
 				})
 			}
+			CIdentDevice::UInputMouse(mode) => {
+				let mut rng = rng();
+				let mut device =
+					uinput::default()?.name(format!("utouch_rs_mouse_{}", rng.random::<u64>()))?;
+				device = if mode == MouseMode::Touchpad {
+					device
+						.event(relative::Position::X)?
+						.event(relative::Position::Y)?
+				} else {
+					device
+						.event(absolute::Position::X)?
+						.min(0)
+						.max(SCREEN_MAX.0 as _)
+						.event(absolute::Position::Y)?
+						.min(0)
+						.max(SCREEN_MAX.1 as _)
+				};
+				let device = device
+					.event(relative::Wheel::Vertical)?
+					.event(relative::Wheel::Horizontal)?
+					.event(controller::Mouse::Left)?
+					.event(controller::Mouse::Right)?
+					.event(controller::Mouse::Middle)?
+					// Ctrl+wheel zoom.
+					.event(keyboard::Key::LeftControl)?
+					.create()?;
+
+				Ok(Self::UInputMouse {
+					device,
+
+					emulator: MouseEmulator::new(mode),
+					events: Vec::with_capacity(8),
+					wheel: (0, 0),
+				})
+			}
+			CIdentDevice::Touchpad => {
+				let mut rng = rng();
+				let touchpad =
+					TouchpadDevice::new(format!("utouch_rs_touchpad_{}", rng.random::<u64>()))?;
+
+				Ok(Self::Touchpad(touchpad))
+			}
+			CIdentDevice::Pen => {
+				let mut rng = rng();
+				let pen = PenDevice::new(format!("utouch_rs_pen_{}", rng.random::<u64>()))?;
+
+				Ok(Self::Pen(pen))
+			}
+			CIdentDevice::Uhid => {
+				let mut rng = rng();
+				let uhid = UhidDevice::new(format!("utouch_rs_hid_{}", rng.random::<u64>()))?;
+
+				Ok(Self::Uhid(uhid))
+			}
+			CIdentDevice::HidGadget => Ok(Self::HidGadget(HidGadget::new()?)),
+			CIdentDevice::Net(payload) => {
+				let addr = var_os("CNET").map_or_else(
+					|| NET_PEER.to_string(),
+					|a| a.to_string_lossy().into_owned(),
+				);
+				let sender = NetSender::new(
+					&addr,
+					NET_TRANSPORT,
+					payload,
+					NET_KEEPALIVE,
+					NET_RECONNECT_INTERVAL,
+				)?;
+
+				Ok(Self::Net(sender))
+			}
+			CIdentDevice::Gamepad => {
+				let mut rng = rng();
+				let device = GamepadEmulator::create_device(format!(
+					"utouch_rs_gamepad_{}",
+					rng.random::<u64>()
+				))?;
+
+				Ok(Self::Gamepad {
+					device,
+
+					pad: GamepadEmulator::new(GAMEPAD_REGIONS),
+					events: Vec::with_capacity(8),
+				})
+			}
 		}
 	}
 
@@ -130,201 +334,781 @@ impl InputDevice {
 		}*/
 	}
 
-	pub fn drop_event(&mut self) {
+	/// Moves (or presses) `contact` in the multitouch slot `a_slot`.
+	fn push_touch_move(
+		device: &mut Device,
+		fingers: &mut [Option<u16>; 12],
+		a_slot: usize,
+		contact: &Contact,
+	) -> anyhow::Result<()> {
+		let id = contact.tracking_id;
+		trace!(
+			"#[touch_move]: id: {}, x: {}, y: {}",
+			id, contact.x, contact.y
+		);
+		let mut x: i32 = contact.x as _;
+		let mut y: i32 = contact.y as _;
+
+		/* INVERS */
+		std::mem::swap(&mut y, &mut x);
+		y = (DISPLAY_HEIGHT as i32) - y;
+
+		let mut is_exists = false;
+		for a_id in fingers.iter().flatten() {
+			if a_id == &id {
+				is_exists = true;
+				break;
+			}
+		}
+
+		device.position(&Slot, a_slot as _)?;
+		if !is_exists && !contact.is_hovering {
+			device.press(&Touch)?;
+			for ref_finger in fingers.iter_mut() {
+				if ref_finger.is_none() {
+					*ref_finger = Some(id);
+					break;
+				}
+			}
+		}
+		device.position(&TrackingId, id as _)?;
+		device.position(&PositionX, x)?;
+		device.position(&PositionY, y)?;
+		device.position(&Distance, contact.is_hovering as _)?;
+		device.synchronize()?;
+
+		Ok(())
+	}
+
+	pub fn free_time(&mut self) -> anyhow::Result<()> {
 		match self {
-			Self::X11OrWayland { .. } => {}
+			Self::X11OrWayland { .. }
+			| Self::UInputMouse { .. }
+			| Self::Touchpad(..)
+			| Self::Pen(..)
+			| Self::Uhid(..)
+			| Self::HidGadget(..)
+			| Self::Net(..)
+			| Self::Gamepad { .. } => Ok(()),
 			Self::UInput {
-				device: _,
-				fingers: _,
+				device,
+				fingers,
 				a_slot,
 			} => {
-				*a_slot += 1;
+				for ref_finger in fingers.iter_mut() {
+					if let Some(id) = ref_finger {
+						device.position(&Slot, *a_slot as _)?;
+						device.position(&TrackingId, *id as _)?;
+						device.release(&Touch)?;
+						device.synchronize()?;
+
+						*ref_finger = None;
+					}
+				}
+
+				while *a_slot != 0 {
+					device.position(&Slot, *a_slot as _)?;
+					device.position(&TrackingId, -1)?;
+					device.synchronize()?;
+
+					*a_slot -= 1;
+				}
+
+				// a_slot 0
+				device.position(&Slot, *a_slot as _)?;
+				device.position(&TrackingId, -1)?;
+				device.synchronize()?;
+
+				*a_slot = 0;
+
+				Ok(())
 			}
 		}
 	}
 
-	pub fn push_move(
-		&mut self,
-		address: u16,
-		id: i8,
-		mut x: u16,
-		mut y: u16,
-	) -> anyhow::Result<()> {
+	#[inline]
+	pub const fn is_animating(&self) -> bool {
+		match self {
+			Self::X11OrWayland { emulator, .. } | Self::UInputMouse { emulator, .. } => {
+				emulator.is_animating()
+			}
+			Self::UInput { .. }
+			| Self::Touchpad(..)
+			| Self::Pen(..)
+			| Self::Uhid(..)
+			| Self::HidGadget(..)
+			| Self::Net(..)
+			| Self::Gamepad { .. } => false,
+		}
+	}
+
+	pub fn tick(&mut self, now: Instant) {
 		match self {
 			Self::X11OrWayland {
 				context,
 				transform,
-				cx,
-				cy,
-				is_add_click,
+				emulator,
+				events,
 			} => {
-				if id > 1 {
-					trace!("#{:#01x} ignore", address);
-					return Ok(());
+				events.clear();
+				emulator.tick(now, events);
+				Self::apply_x11(context, *transform, events);
+			}
+			Self::UInputMouse {
+				device,
+				emulator,
+				events,
+				wheel,
+			} => {
+				events.clear();
+				emulator.tick(now, events);
+				if let Err(e) = Self::apply_uinput_mouse(device, wheel, events) {
+					error!("mouse, err: {:?}", e);
 				}
+			}
+			Self::UInput { .. }
+			| Self::Touchpad(..)
+			| Self::Pen(..)
+			| Self::Uhid(..)
+			| Self::HidGadget(..)
+			| Self::Net(..)
+			| Self::Gamepad { .. } => {}
+		}
+	}
 
-				if x != *cx || y != *cy {
-					*cx = x;
-					*cy = y;
+	/// Backend name for logs.
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::X11OrWayland { .. } => "x11",
+			Self::UInput { .. } => "uinput",
+			Self::UInputMouse { .. } => "uinput mouse",
+			Self::Touchpad(..) => "touchpad",
+			Self::Pen(..) => "pen",
+			Self::Uhid(..) => "uhid",
+			Self::HidGadget(..) => "hid gadget",
+			Self::Net(..) => "net",
+			Self::Gamepad { .. } => "gamepad",
+		}
+	}
 
-					/* INVERS */
-					if NEEDS_COORDINATE_INVERSION {
-						std::mem::swap(&mut y, &mut x);
-					}
+	pub fn set_transform(&mut self, new_transform: CTypeTransformCoordinates) {
+		match self {
+			Self::X11OrWayland { transform, .. } => *transform = new_transform,
+			Self::UInput { .. }
+			| Self::UInputMouse { .. }
+			| Self::Touchpad(..)
+			| Self::Pen(..)
+			| Self::Uhid(..)
+			| Self::HidGadget(..)
+			| Self::Net(..)
+			| Self::Gamepad { .. } => {
+				info!("transform is not supported by {}, ignore", self.name())
+			}
+		}
+	}
 
-					let m_x;
-					let m_y;
+	pub fn set_mouse_mode(&mut self, mode: MouseMode) -> anyhow::Result<()> {
+		match self {
+			Self::X11OrWayland { .. } => {}
+			// The device is either relative or absolute.
+			Self::UInputMouse { emulator, .. }
+				if (emulator.mode() == MouseMode::Touchpad) == (mode == MouseMode::Touchpad) => {}
+			Self::UInput { .. }
+			| Self::UInputMouse { .. }
+			| Self::Touchpad(..)
+			| Self::Pen(..)
+			| Self::Uhid(..)
+			| Self::HidGadget(..)
+			| Self::Net(..)
+			| Self::Gamepad { .. } => {
+				info!(
+					"mouse mode {:?} is not supported by the device, ignore",
+					mode
+				);
+				return Ok(());
+			}
+		}
 
-					match transform {
-						CTypeTransformCoordinates::Ver01 => {
-							m_x = x as i32;
-							m_y = (DISPLAY_HEIGHT as i32) - (y as i32);
-						}
-						CTypeTransformCoordinates::Ver02 => {
-							#[inline]
-							const fn map(
-								x: i32,
-								in_min: i32,
-								in_max: i32,
-								out_min: i32,
-								out_max: i32,
-							) -> i32 {
-								(x - in_min) * (out_max - out_min) / (in_max - in_min) + out_min
-							}
+		self.cancel()?;
+		if let Self::X11OrWayland { emulator, .. } | Self::UInputMouse { emulator, .. } = self {
+			emulator.set_mode(mode);
+		}
 
-							m_x = map(x as i32, 0, DISPLAY_WIDTH as _, 0, DISPLAY_HEIGHT as _);
-							m_y = map(
-								y as i32,
-								DISPLAY_HEIGHT as _,
-								0,
-								0,
-								(DISPLAY_HEIGHT as i32) / 2,
-							);
-						}
-					}
+		Ok(())
+	}
 
-					trace!(
-						"#[x11_move]: [x: {}, y: {}] => [m_x: {}, m_y: {}]",
-						x, y, m_x, m_y
-					);
-					if let Err(e) = context.mouse_move_abs(m_x, m_y) {
-						error!("mouse, err: {:?}", e);
-					}
+	/// Bytes as read from the controller, before decoding.
+	pub fn push_raw(&mut self, data: &[u8]) {
+		if let Self::Net(sender) = self {
+			sender.push_raw(data);
+		}
+	}
 
-					*is_add_click = true;
-				}
+	/// Drops the current contacts without any click, a gesture took them over.
+	pub fn cancel(&mut self) -> anyhow::Result<()> {
+		match self {
+			Self::X11OrWayland {
+				context,
+				transform,
+				emulator,
+				events,
+			} => {
+				events.clear();
+				emulator.cancel(events);
+				Self::apply_x11(context, *transform, events);
 
 				Ok(())
 			}
-			Self::UInput {
+			Self::UInput { .. } => self.free_time(),
+			Self::UInputMouse {
 				device,
-				fingers,
-				a_slot,
+				emulator,
+				events,
+				wheel,
 			} => {
-				trace!("#[touch_move]: id: {}, x: {}, y: {}", id, x, y);
-				let mut x: i32 = x as _;
-				let mut y: i32 = y as _;
-
-				/* INVERS */
-				std::mem::swap(&mut y, &mut x);
-				y = (DISPLAY_HEIGHT as i32) - y;
-
-				let mut is_exists = false;
-				for a_id in fingers.iter().flatten() {
-					if a_id == &id {
-						is_exists = true;
-						break;
-					}
-				}
+				events.clear();
+				emulator.cancel(events);
 
-				device.position(&Slot, *a_slot as _)?;
-				if !is_exists {
-					device.press(&Touch)?;
-					for ref_finger in fingers.iter_mut() {
-						if ref_finger.is_none() {
-							*ref_finger = Some(id);
-							break;
-						}
-					}
-				}
-				device.position(&TrackingId, id as _)?;
-				device.position(&PositionX, x)?;
-				device.position(&PositionY, y)?;
-				device.synchronize()?;
+				Self::apply_uinput_mouse(device, wheel, events)
+			}
+			Self::Touchpad(touchpad) => touchpad.push_frame(&[]),
+			Self::Pen(pen) => pen.push_frame(&[], &[]),
+			Self::Uhid(uhid) => uhid.push_frame(&[]),
+			Self::HidGadget(gadget) => gadget.push_frame(&[]),
+			Self::Net(sender) => {
+				sender.push_frame(0, &[], &[]);
 
 				Ok(())
 			}
+			Self::Gamepad {
+				device,
+				pad,
+				events,
+			} => {
+				events.clear();
+				pad.cancel(events);
+
+				Self::apply_gamepad(device, events)
+			}
 		}
 	}
 
-	pub fn free_time(&mut self) -> anyhow::Result<()> {
+	pub fn push_frame(
+		&mut self,
+		address: u16,
+		frame: &[Contact],
+		hover: &[Contact],
+		now: Instant,
+	) -> anyhow::Result<()> {
 		match self {
 			Self::X11OrWayland {
 				context,
-				transform: _,
-				cx: _,
-				cy: _,
-				is_add_click,
-			} if is_add_click == &true => {
-				*is_add_click = false;
-				trace!("#[x11_click]");
-
-				context.mouse_click(MouseButton::Left)?;
+				transform,
+				emulator,
+				events,
+			} => {
+				events.clear();
+				emulator.process(frame, now, events);
+				if frame.is_empty()
+					&& let Some(contact) = hover.first()
+				{
+					emulator.hover(contact, events);
+				}
+
+				Self::apply_x11(context, *transform, events);
+
 				Ok(())
 			}
-			Self::X11OrWayland { .. } => Ok(()),
 			Self::UInput {
 				device,
 				fingers,
 				a_slot,
 			} => {
-				for ref_finger in fingers.iter_mut() {
-					if let Some(id) = ref_finger {
-						device.position(&Slot, *a_slot as _)?;
-						device.position(&TrackingId, *id as _)?;
-						device.release(&Touch)?;
-						device.synchronize()?;
+				for contact in frame.iter().chain(hover.iter()) {
+					let _e = Self::push_touch_move(device, fingers, *a_slot, contact);
+					*a_slot += 1;
+				}
+				if frame.is_empty() && hover.is_empty() {
+					self.free_time()?;
+				}
 
-						*ref_finger = None;
-					}
+				Ok(())
+			}
+			Self::UInputMouse {
+				device,
+				emulator,
+				events,
+				wheel,
+			} => {
+				events.clear();
+				emulator.process(frame, now, events);
+				if frame.is_empty()
+					&& let Some(contact) = hover.first()
+				{
+					emulator.hover(contact, events);
 				}
 
-				while *a_slot != 0 {
-					device.position(&Slot, *a_slot as _)?;
-					device.position(&TrackingId, -1)?;
+				Self::apply_uinput_mouse(device, wheel, events)
+			}
+			Self::Touchpad(touchpad) => touchpad.push_frame(frame),
+			Self::Pen(pen) => pen.push_frame(frame, hover),
+			Self::Uhid(uhid) => uhid.push_frame(frame),
+			Self::HidGadget(gadget) => gadget.push_frame(frame),
+			Self::Net(sender) => {
+				sender.push_frame(address, frame, hover);
+
+				Ok(())
+			}
+			Self::Gamepad {
+				device,
+				pad,
+				events,
+			} => {
+				events.clear();
+				pad.process(frame, events);
+
+				Self::apply_gamepad(device, events)
+			}
+		}
+	}
+
+	fn apply_uinput_mouse(
+		device: &mut Device,
+		wheel: &mut (i32, i32),
+		events: &[MouseEvent],
+	) -> anyhow::Result<()> {
+		if events.is_empty() {
+			return Ok(());
+		}
+
+		for event in events.iter() {
+			match *event {
+				// Only one of them is produced, see `CIdentDevice::UInputMouse`.
+				MouseEvent::Move { x, y } => {
+					let (x, y) = screen_point(x, y);
+					trace!("#[uinput_mouse] x: {}, y: {}", x, y);
+					device.send(absolute::Position::X, x as _)?;
+					device.send(absolute::Position::Y, y as _)?;
+				}
+				MouseEvent::MoveRel { dx, dy } => {
+					let (dx, dy) = screen_delta(dx, dy);
+					trace!("#[uinput_mouse] dx: {}, dy: {}", dx, dy);
+					device.send(relative::Position::X, dx)?;
+					device.send(relative::Position::Y, dy)?;
+				}
+				MouseEvent::Down(button) => device.send(Self::uinput_button(button), 1)?,
+				MouseEvent::Up(button) => device.send(Self::uinput_button(button), 0)?,
+				MouseEvent::Click(button) => {
+					device.send(Self::uinput_button(button), 1)?;
+					device.synchronize()?;
+					device.send(Self::uinput_button(button), 0)?;
+				}
+				MouseEvent::Scroll { dx, dy } => {
+					let (dx, dy) = screen_delta(dx, dy);
+					wheel.0 += dx;
+					wheel.1 += dy;
+					// Whole notches only, positive REL_WHEEL scrolls up.
+					let (nx, ny) = (wheel.0 / 120, wheel.1 / 120);
+					wheel.0 -= nx * 120;
+					wheel.1 -= ny * 120;
+					if nx != 0 {
+						device.send(relative::Wheel::Horizontal, nx)?;
+					}
+					if ny != 0 {
+						device.send(relative::Wheel::Vertical, -ny)?;
+					}
+				}
+				MouseEvent::Zoom(steps) => {
+					device.press(&keyboard::Key::LeftControl)?;
+					device.send(relative::Wheel::Vertical, steps)?;
 					device.synchronize()?;
+					device.release(&keyboard::Key::LeftControl)?;
+				}
+			}
+		}
+		device.synchronize()?;
 
-					*a_slot -= 1;
+		Ok(())
+	}
+
+	#[inline]
+	const fn uinput_button(button: mouse::MouseButton) -> controller::Mouse {
+		match button {
+			mouse::MouseButton::Left => controller::Mouse::Left,
+			mouse::MouseButton::Right => controller::Mouse::Right,
+			mouse::MouseButton::Middle => controller::Mouse::Middle,
+		}
+	}
+
+	fn apply_gamepad(device: &mut Device, events: &[PadEvent]) -> anyhow::Result<()> {
+		if events.is_empty() {
+			return Ok(());
+		}
+
+		for event in events.iter() {
+			trace!("#[gamepad] {:?}", event);
+			match *event {
+				PadEvent::Axis(axis, value) => device.send(axis, value)?,
+				PadEvent::Hat(hat, value) => device.send(hat, value)?,
+				PadEvent::Button(button, true) => device.press(&button)?,
+				PadEvent::Button(button, false) => device.release(&button)?,
+			}
+		}
+		device.synchronize()?;
+
+		Ok(())
+	}
+
+	fn apply_x11(
+		context: &mut Context,
+		transform: CTypeTransformCoordinates,
+		events: &[MouseEvent],
+	) {
+		for event in events.iter() {
+			let result = match *event {
+				MouseEvent::Move { x, y } => {
+					let (m_x, m_y) = Self::transform_x11(transform, x, y);
+					context.mouse_move_abs(m_x, m_y)
+				}
+				MouseEvent::MoveRel { dx, dy } => {
+					let (dx, dy) = screen_delta(dx, dy);
+					trace!("#[x11_move_rel] dx: {}, dy: {}", dx, dy);
+					context.mouse_move_rel(dx, dy)
 				}
+				MouseEvent::Down(button) => {
+					trace!("#[x11_down] {:?}", button);
+					context.mouse_down(Self::x11_button(button))
+				}
+				MouseEvent::Up(button) => {
+					trace!("#[x11_up] {:?}", button);
+					context.mouse_up(Self::x11_button(button))
+				}
+				MouseEvent::Click(button) => {
+					trace!("#[x11_click] {:?}", button);
+					context.mouse_click(Self::x11_button(button))
+				}
+				MouseEvent::Scroll { dx, dy } => {
+					let (dx, dy) = screen_delta(dx, dy);
+					trace!("#[x11_scroll] dx: {}, dy: {}", dx, dy);
+					context.mouse_scroll(dx, dy)
+				}
+				MouseEvent::Zoom(steps) => {
+					trace!("#[x11_zoom] {}", steps);
+					context.key_down(Key::Control).and_then(|_| {
+						let result = context.mouse_scroll(0, -steps * 120);
+						context.key_up(Key::Control).and(result)
+					})
+				}
+			};
+			if let Err(e) = result {
+				error!("mouse, err: {:?}", e);
+			}
+		}
+	}
 
-				// a_slot 0
-				device.position(&Slot, *a_slot as _)?;
-				device.position(&TrackingId, -1)?;
-				device.synchronize()?;
+	#[inline]
+	const fn x11_button(button: mouse::MouseButton) -> MouseButton {
+		match button {
+			mouse::MouseButton::Left => MouseButton::Left,
+			mouse::MouseButton::Right => MouseButton::Right,
+			mouse::MouseButton::Middle => MouseButton::Middle,
+		}
+	}
 
-				*a_slot = 0;
+	fn transform_x11(transform: CTypeTransformCoordinates, mut x: u16, mut y: u16) -> (i32, i32) {
+		/* INVERS */
+		if NEEDS_COORDINATE_INVERSION {
+			std::mem::swap(&mut y, &mut x);
+		}
 
-				Ok(())
+		let m_x;
+		let m_y;
+
+		match transform {
+			CTypeTransformCoordinates::Ver01 => {
+				m_x = x as i32;
+				m_y = (DISPLAY_HEIGHT as i32) - (y as i32);
+			}
+			CTypeTransformCoordinates::Ver02 => {
+				#[inline]
+				const fn map(x: i32, in_min: i32, in_max: i32, out_min: i32, out_max: i32) -> i32 {
+					(x - in_min) * (out_max - out_min) / (in_max - in_min) + out_min
+				}
+
+				m_x = map(x as i32, 0, DISPLAY_WIDTH as _, 0, DISPLAY_HEIGHT as _);
+				m_y = map(
+					y as i32,
+					DISPLAY_HEIGHT as _,
+					0,
+					0,
+					(DISPLAY_HEIGHT as i32) / 2,
+				);
 			}
 		}
+
+		trace!(
+			"#[x11_move]: [x: {}, y: {}] => [m_x: {}, m_y: {}]",
+			x, y, m_x, m_y
+		);
+		(m_x, m_y)
 	}
 
 	pub fn init_press(&mut self) -> anyhow::Result<()> {
 		match self {
-			Self::X11OrWayland { .. /*context, transform, cx, cy, is_add_click*/ } => {
+			Self::X11OrWayland { .. /*context, transform, emulator, events*/ } => {
 				Ok(())
 			},
 			Self::UInput { .. /*device, fingers, a_slot*/ } => {
 				Ok(())
 			},
+			Self::UInputMouse { .. } | Self::Touchpad(..) | Self::Pen(..) | Self::Uhid(..) | Self::HidGadget(..) | Self::Net(..) | Self::Gamepad { .. } => Ok(()),
 		}
 	}
 }
 
+/// Everything after the decoder: virtual buttons, pipeline, gestures and the backend.
+pub struct Frontend {
+	// `None` while recording a gesture, nothing is sent anywhere then.
+	input_device: Option<InputDevice>,
+	buttons: VirtualButtons,
+	pipeline: Pipeline,
+	gestures: GestureEngine,
+	actions: ActionRunner,
+	hover: Vec<Contact>,
+
+	// `utouch_rs record-gesture <name>`, the next stroke is stored as a template.
+	record_gesture: Option<String>,
+	recorder: StrokeRecorder,
+	// `utouch_rs record <file>`.
+	capture: Option<CaptureWriter>,
+}
+
+impl Frontend {
+	pub fn new(
+		c_ident_device: CIdentDevice,
+		record_gesture: Option<String>,
+		capture: Option<CaptureWriter>,
+	) -> anyhow::Result<Self> {
+		let input_device = match record_gesture {
+			Some(_) => None,
+			None => Some(InputDevice::new(c_ident_device)?),
+		};
+
+		Ok(Self {
+			input_device,
+			buttons: VirtualButtons::from_config()?,
+			pipeline: Pipeline::new(),
+			gestures: GestureEngine::from_config(),
+			actions: ActionRunner::new(),
+			hover: Vec::with_capacity(12),
+
+			record_gesture,
+			recorder: StrokeRecorder::new(),
+			capture,
+		})
+	}
+
+	/// Waits for the next message of a source, advancing animations meanwhile.
+	/// `None` once the source is gone.
+	pub fn wait<T>(&mut self, rx: &Receiver<T>) -> Option<T> {
+		while self
+			.input_device
+			.as_ref()
+			.is_some_and(InputDevice::is_animating)
+			|| self.buttons.is_held()
+		{
+			match rx.recv_timeout(ANIMATION_INTERVAL) {
+				Ok(a) => {
+					self.tick(Instant::now());
+					return Some(a);
+				}
+				Err(RecvTimeoutError::Timeout) => {
+					let now = Instant::now();
+					self.tick(now);
+					if let Err(e) = self.buttons.tick(now) {
+						error!("#[button] err: {:?}", e);
+					}
+				}
+				Err(RecvTimeoutError::Disconnected) => return None,
+			}
+		}
+
+		let a = rx.recv().ok()?;
+		self.tick(Instant::now());
+		Some(a)
+	}
+
+	#[inline]
+	fn tick(&mut self, now: Instant) {
+		if let Some(ref mut input_device) = self.input_device {
+			input_device.tick(now);
+		}
+	}
+
+	fn push_capture(&mut self, event: CaptureEvent) {
+		if let Some(ref mut capture) = self.capture
+			&& let Err(e) = capture.push(Instant::now(), &event)
+		{
+			error!("#[capture] err: {:?}", e);
+		}
+	}
+
+	/// Edge on the interrupt pin, only recorded.
+	#[inline]
+	pub fn push_int(&mut self) {
+		self.push_capture(CaptureEvent::Int);
+	}
+
+	/// Bytes as read from the controller, see `InputDevice::push_raw`.
+	pub fn push_raw(&mut self, data: &[u8]) {
+		if self.capture.is_some() {
+			self.push_capture(CaptureEvent::Read(data.to_vec()));
+		}
+		if let Some(ref mut input_device) = self.input_device {
+			input_device.push_raw(data);
+		}
+	}
+
+	/// Contacts that already went through the pipeline and gestures of another machine.
+	pub fn push_remote(&mut self, address: u16, frame: &[Contact], hover: &[Contact]) {
+		let Some(ref mut input_device) = self.input_device else {
+			return;
+		};
+		input_device.initialize_event_aggregator();
+		if self.actions.is_input_enabled() {
+			let _e = input_device.push_frame(address, frame, hover, Instant::now());
+		}
+	}
+
+	/// One decoded frame in raw sensor coordinates, empty once every contact is lifted.
+	/// `Break` once the recorded gesture is saved.
+	pub fn process(
+		&mut self,
+		address: u16,
+		frame: &mut Vec<Contact>,
+	) -> anyhow::Result<ControlFlow<()>> {
+		if let Some(ref mut input_device) = self.input_device {
+			input_device.initialize_event_aggregator();
+		}
+		if !HOVER {
+			frame.retain(|a| !a.is_hovering);
+		}
+
+		let now = Instant::now();
+		if let Err(e) = self.buttons.process(frame, now) {
+			error!("#[button] err: {:?}", e);
+		}
+		self.pipeline.process(frame, now);
+		self.hover.clear();
+		frame.retain(|a| {
+			if a.is_hovering {
+				self.hover.push(*a);
+			}
+			!a.is_hovering
+		});
+
+		if let Some(ref name) = self.record_gesture {
+			if !frame.is_empty() {
+				self.recorder.push(frame);
+			} else if !self.recorder.is_empty() {
+				let template = Template::new(name.clone(), self.recorder.take());
+				template.save(Path::new(TEMPLATE_DIR))?;
+				info!(
+					"record-gesture: {:?} saved, strokes: {}",
+					name,
+					template.strokes().len()
+				);
+
+				return Ok(ControlFlow::Break(()));
+			}
+
+			return Ok(ControlFlow::Continue(()));
+		}
+		let Some(ref mut input_device) = self.input_device else {
+			return Ok(ControlFlow::Continue(()));
+		};
+
+		let is_captured = self.gestures.is_captured();
+		if let Some(action) = self.gestures.process(frame, now) {
+			if !is_captured {
+				// The gesture owns the contacts until they are lifted.
+				let _e = input_device.cancel();
+			}
+			if let Err(e) = self.actions.run(action, frame, input_device, now) {
+				error!("#[action] {:?}, err: {:?}", action, e);
+			}
+		}
+		if !self.gestures.is_captured() && self.actions.is_input_enabled() {
+			let _e = input_device.push_frame(address, frame, &self.hover, now);
+		}
+
+		Ok(ControlFlow::Continue(()))
+	}
+}
+
+fn source_from_env() -> CSource {
+	match var_os("CSOURCE") {
+		Some(a) if a == osstr!("EVDEV") || a == osstr!("INPUT") => CSource::Evdev,
+		Some(a) if a == osstr!("SERIAL") || a == osstr!("UART") => CSource::Serial,
+		Some(a) if a == osstr!("NET") || a == osstr!("NETWORK") => CSource::Net,
+
+		_ => CSource::I2c,
+	}
+}
+
+fn mouse_mode_from_env() -> MouseMode {
+	let mouse_mode = match var_os("CMOUSE") {
+		Some(a) if a == osstr!("CLICK") || a == osstr!("TAP") => MouseMode::Click,
+		Some(a) if a == osstr!("DRAG") => MouseMode::Drag,
+		Some(a) if a == osstr!("SCROLL") || a == osstr!("KIOSK") => MouseMode::Scroll,
+		Some(a) if a == osstr!("TOUCHPAD") || a == osstr!("TRACKPAD") => MouseMode::Touchpad,
+
+		_ => MouseMode::Drag,
+	};
+	info!("cmouse: {:?}", mouse_mode);
+
+	mouse_mode
+}
+
 fn main() -> anyhow::Result<()> {
 	env_logger::try_init()?;
 	info!("utouch_rs: ");
 
+	let mut record_gesture = None;
+	let mut capture = None;
+	let mut replay = None;
+	match args().nth(1) {
+		// `utouch_rs record-gesture <name>`, store the next stroke as a template and exit.
+		Some(a) if a == "record-gesture" => match args().nth(2) {
+			Some(name) if !name.is_empty() && !name.contains(['/', '.']) => {
+				info!("record-gesture: {:?}, dir: {:?}", name, TEMPLATE_DIR);
+				record_gesture = Some(name);
+			}
+			_ => anyhow::bail!("usage: utouch_rs record-gesture <name>"),
+		},
+		// `utouch_rs record <file>`, run as usual and write every read buffer to a capture.
+		Some(a) if a == "record" => match args().nth(2) {
+			Some(path) => capture = Some(CaptureWriter::create(Path::new(&path))?),
+			None => anyhow::bail!("usage: utouch_rs record <file>"),
+		},
+		// `utouch_rs replay <file> [speed]`, a capture instead of the controller.
+		Some(a) if a == "replay" => match (args().nth(2), args().nth(3)) {
+			(Some(path), speed) => {
+				let speed = match speed {
+					Some(a) => a
+						.parse::<f32>()
+						.map_err(|e| anyhow::anyhow!("invalid speed: {:?}, {}", a, e))?,
+					None => 1.0,
+				};
+				info!("replay: {:?}, speed: {}", path, speed);
+				replay = Some((Capture::load(Path::new(&path))?, speed));
+			}
+			_ => anyhow::bail!("usage: utouch_rs replay <file> [speed, 0 - no delays]"),
+		},
+		Some(a) => anyhow::bail!("unknown command: {:?}", a),
+		None => {}
+	}
+
 	let mut owned_a = OsString::new();
 	let c_ident_device = match var_os("CDEVICE").map(|a| {
 		owned_a = a;
@@ -334,8 +1118,6 @@ fn main() -> anyhow::Result<()> {
 			if a == osstr!("X11_MOUSE")
 				|| a == osstr!("X11")
 				|| a == osstr!("MOUSE")
-				|| a == osstr!("WAYLAND_MOUSE")
-				|| a == osstr!("WAYLAND")
 				|| a == osstr!("UINPUT") =>
 		{
 			let c_type_transform_coordinates = match var_os("CTYPE") {
@@ -350,6 +1132,8 @@ fn main() -> anyhow::Result<()> {
 			};
 			info!("ctype: {:?}", c_type_transform_coordinates);
 
+			let mouse_mode = mouse_mode_from_env();
+
 			match var_os("DISPLAY") {
 				Some(a) => {
 					info!("DISPLAY={:?}", a);
@@ -378,6 +1162,7 @@ fn main() -> anyhow::Result<()> {
 							Ok(entry) => {
 								for entry in entry.flatten() {
 									let path = entry.path();
+									#[allow(clippy::collapsible_if)]
 									if path.is_file() {
 										if let Some(filename) = path.file_name() {
 											// todo, osstr, linux
@@ -405,16 +1190,232 @@ fn main() -> anyhow::Result<()> {
 				}
 			};
 
-			CIdentDevice::X11OrWayland(c_type_transform_coordinates)
+			CIdentDevice::X11OrWayland(c_type_transform_coordinates, mouse_mode)
 		}
 		Some(a) if a == osstr!("UINPUT") || a == osstr!("LINUX") || a == osstr!("TOUCH") => {
 			CIdentDevice::UInput
 		}
+		Some(a)
+			if a == osstr!("WAYLAND")
+				|| a == osstr!("WAYLAND_MOUSE")
+				|| a == osstr!("UINPUT_POINTER") =>
+		{
+			CIdentDevice::UInputMouse(mouse_mode_from_env())
+		}
+		Some(a) if a == osstr!("UINPUT_MOUSE") || a == osstr!("TOUCHPAD") => {
+			CIdentDevice::UInputMouse(MouseMode::Touchpad)
+		}
+		Some(a)
+			if a == osstr!("LIBINPUT")
+				|| a == osstr!("LIBINPUT_TOUCHPAD")
+				|| a == osstr!("UINPUT_TOUCHPAD") =>
+		{
+			CIdentDevice::Touchpad
+		}
+		Some(a) if a == osstr!("PEN") || a == osstr!("TABLET") || a == osstr!("STYLUS") => {
+			CIdentDevice::Pen
+		}
+		Some(a) if a == osstr!("UHID") || a == osstr!("HID") => CIdentDevice::Uhid,
+		Some(a) if a == osstr!("HID_GADGET") || a == osstr!("USB_GADGET") => {
+			CIdentDevice::HidGadget
+		}
+		Some(a) if a == osstr!("NET") || a == osstr!("NET_CONTACTS") => {
+			CIdentDevice::Net(NetPayload::Contacts)
+		}
+		Some(a) if a == osstr!("NET_RAW") => CIdentDevice::Net(NetPayload::Raw),
+		Some(a) if a == osstr!("GAMEPAD") || a == osstr!("JOYSTICK") => CIdentDevice::Gamepad,
 
 		_ => CIdentDevice::UInput,
 	};
 
 	info!("cdevice: {:?}", c_ident_device);
+	info!("#[cdevice] init...");
+	let mut frontend = Frontend::new(c_ident_device, record_gesture, capture)?;
+	sleep(Duration::from_millis(300));
+
+	if let Some((capture, speed)) = replay {
+		return run_replay(&mut frontend, capture, speed);
+	}
+	let c_source = source_from_env();
+	info!("csource: {:?}", c_source);
+	match c_source {
+		CSource::I2c => run_i2c(&mut frontend),
+		CSource::Evdev => run_evdev(&mut frontend),
+		CSource::Serial => run_serial(&mut frontend),
+		CSource::Net => run_net(&mut frontend),
+	}
+}
+
+fn run_replay(frontend: &mut Frontend, capture: Capture, speed: f32) -> anyhow::Result<()> {
+	let (tx, rx) = channel::<StreamEvent>();
+	spawn(move || capture::replay(capture, speed, tx));
+
+	info!("#[capture] loop:");
+	run_stream(frontend, &rx)
+}
+
+/// Contacts of a complete `BuildReader` line, `true` if it had any chunk.
+fn decode_line(line: &[u8], endb: u8, frame: &mut Vec<Contact>) -> bool {
+	let mut is_evented = false;
+	frame.clear();
+	Reader::search(line.iter().copied(), |data| {
+		is_evented = true;
+
+		if endb == 0 {
+			frame.push(Contact::from_chunk(data));
+		}
+	});
+
+	is_evented
+}
+
+/// Bytes of a transport through `BuildReader`.
+struct StreamDecoder {
+	builder: BuildReader,
+	frame: Vec<Contact>,
+}
+
+impl StreamDecoder {
+	fn new() -> Self {
+		Self {
+			builder: BuildReader::empty(),
+			frame: Vec::with_capacity(12),
+		}
+	}
+
+	fn push(
+		&mut self,
+		frontend: &mut Frontend,
+		event: StreamEvent,
+	) -> anyhow::Result<ControlFlow<()>> {
+		match event {
+			StreamEvent::Data(data) => {
+				frontend.push_raw(&data);
+				for a in data {
+					let result = self.builder.write(a);
+					if result.is_end_line() {
+						let (address, line, endb) = self.builder.get_line();
+						if endb != 0 {
+							trace!("#endbyte {:?}", endb);
+						}
+						let is_evented = decode_line(line, endb, &mut self.frame);
+						if endb != 0 && is_evented {
+							// Not a touch report, contacts and pipeline state stay as they are.
+						} else if frontend.process(address, &mut self.frame)?.is_break() {
+							return Ok(ControlFlow::Break(()));
+						}
+
+						self.builder.clear();
+					} else if result.is_ignore_and_skipdata() {
+						break;
+					}
+				}
+			}
+			StreamEvent::Idle => self.builder.clear(),
+			StreamEvent::Lost => {
+				self.builder.clear();
+				self.frame.clear();
+				return frontend.process(0, &mut self.frame);
+			}
+		}
+
+		Ok(ControlFlow::Continue(()))
+	}
+}
+
+/// Feeds the bytes of a transport through `BuildReader` until it ends.
+fn run_stream(frontend: &mut Frontend, rx: &Receiver<StreamEvent>) -> anyhow::Result<()> {
+	let mut decoder = StreamDecoder::new();
+	while let Some(event) = frontend.wait(rx) {
+		if decoder.push(frontend, event)?.is_break() {
+			break;
+		}
+	}
+
+	Ok(())
+}
+
+fn run_net(frontend: &mut Frontend) -> anyhow::Result<()> {
+	let addr = var_os("CNET").map_or_else(
+		|| NET_LISTEN.to_string(),
+		|a| a.to_string_lossy().into_owned(),
+	);
+	let listener = NetListener::bind(&addr, NET_TRANSPORT)?;
+
+	let (tx, rx) = channel::<NetEvent>();
+	spawn(move || {
+		if let Err(e) = listener.run(NET_LINK_TIMEOUT, tx) {
+			error!("#[net] err: {:?}", e);
+		}
+	});
+
+	info!("#[net] loop:");
+	let mut decoder = StreamDecoder::new();
+	while let Some(event) = frontend.wait(&rx) {
+		match event {
+			NetEvent::Stream(event) => {
+				if decoder.push(frontend, event)?.is_break() {
+					break;
+				}
+			}
+			NetEvent::Contacts {
+				address,
+				frame,
+				hover,
+			} => frontend.push_remote(address, &frame, &hover),
+		}
+	}
+
+	Ok(())
+}
+
+fn run_serial(frontend: &mut Frontend) -> anyhow::Result<()> {
+	let path = var_os("CSERIAL").map_or_else(|| PathBuf::from(SERIAL_PATH), PathBuf::from);
+
+	let (tx, rx) = channel::<StreamEvent>();
+	spawn(move || {
+		serial::run(
+			&path,
+			SERIAL_BAUD,
+			SERIAL_FRAMING,
+			SERIAL_IDLE_TIMEOUT,
+			SERIAL_RECONNECT_INTERVAL,
+			tx,
+		)
+	});
+
+	info!("#[serial] loop:");
+	run_stream(frontend, &rx)
+}
+
+fn run_evdev(frontend: &mut Frontend) -> anyhow::Result<()> {
+	let path = var_os("CEVDEV").map_or_else(|| PathBuf::from(EVDEV_PATH), PathBuf::from);
+	let mut source = EvdevSource::open(&path, EVDEV_GRAB)?;
+
+	let (tx, rx) = channel::<io::Result<Vec<Contact>>>();
+	spawn(move || {
+		loop {
+			let mut frame = Vec::with_capacity(12);
+			let result = source.read_frame(&mut frame).map(|_| frame);
+			let is_err = result.is_err();
+			if tx.send(result).is_err() || is_err {
+				break; // END CTHREAD
+			}
+		}
+	});
+
+	info!("#[evdev] loop:");
+	while let Some(frame) = frontend.wait(&rx) {
+		let mut frame = frame?;
+		if frontend.process(0, &mut frame)?.is_break() {
+			break;
+		}
+	}
+
+	Ok(())
+}
+
+fn run_i2c(frontend: &mut Frontend) -> anyhow::Result<()> {
 	info!("attention, Interrupt int is not serviced.");
 	info!("");
 	let gpio = Gpio::new()?;
@@ -472,7 +1473,8 @@ fn main() -> anyhow::Result<()> {
 	let mut builder = BuildReader::empty();
 	let mut i2carray = vec![0u8; 60];
 
-	let (tx, rx) = channel::<()>();
+	// `true` - an edge, `false` - the poll timed out.
+	let (tx, rx) = channel::<bool>();
 	let wait_init_thread = Arc::new(Barrier::new(1 + 1));
 	spawn(enc!((wait_init_thread) move || {
 		{
@@ -485,7 +1487,7 @@ fn main() -> anyhow::Result<()> {
 			if let Err(ref e) = int {
 				error!("Err, {:?}", e);
 			}
-			if tx.send(()).is_err() {
+			if tx.send(matches!(int, Ok(Some(..)))).is_err() {
 				break; // END CTHREAD
 			}
 		}
@@ -497,15 +1499,15 @@ fn main() -> anyhow::Result<()> {
 
 	// RES+INTERRUPT+DECODER
 	let mut is_addition_interrupt = false;
-	info!("#[cdevice] init...");
-	let mut input_device = InputDevice::new(c_ident_device)?;
-	sleep(Duration::from_millis(300));
+	let mut frame = Vec::with_capacity(12);
 	info!("#[cdevice] loop:");
 	loop {
 		if !is_addition_interrupt {
-			if rx.recv().is_err() {
-				// WAIT INTERRUPT
-				break;
+			// WAIT INTERRUPT
+			match frontend.wait(&rx) {
+				Some(true) => frontend.push_int(),
+				Some(false) => {}
+				None => break,
 			}
 		} else {
 			is_addition_interrupt = false;
@@ -529,13 +1531,13 @@ fn main() -> anyhow::Result<()> {
 		if data.is_empty() {
 			continue;
 		}
+		frontend.push_raw(data);
 
 		trace!("#[i2c, {:#01x}] data: {:?}", I2C_ADDR, data);
 		for a in data.iter() {
 			let result = builder.write(*a);
 
 			if result.is_end_line() {
-				is_addition_interrupt = false;
 				let (address, line, endb) = builder.get_line();
 
 				/*if line.len() > 0 {
@@ -544,27 +1546,15 @@ fn main() -> anyhow::Result<()> {
 				if endb != 0 {
 					trace!("#endbyte {:?}", endb);
 				}
-				input_device.initialize_event_aggregator();
-				let mut is_evented = false;
-				Reader::search(line.iter().copied(), |data| {
-					is_addition_interrupt = true;
-					is_evented = true;
-
-					//println!("#endbyte {:?}", endb);
-					if endb == 0 {
-						let id: i8 = (data[1] as i8) - 16;
-
-						let x: u16 = u16::from_le_bytes([data[2], data[3] & 0b0000_1111]);
-						let y: u16 = u16::from_le_bytes([data[4], data[3] & 0b1111_0000]) << 4;
-
-						let _e = input_device.push_move(address, id, x, y);
-					} else if endb == 1 {
-						//let _e = cdevice.free_time();
-					}
-					input_device.drop_event();
-				});
-				if !is_evented {
-					let _e = input_device.free_time();
+				let is_evented = decode_line(line, endb, &mut frame);
+				is_addition_interrupt = is_evented;
+				if endb != 0 && is_evented {
+					// Not a touch report, contacts and pipeline state stay as they are.
+					builder.clear();
+					continue;
+				}
+				if frontend.process(address, &mut frame)?.is_break() {
+					return Ok(());
 				}
 
 				builder.clear();
@@ -588,3 +1578,40 @@ fn main() -> anyhow::Result<()> {
 
 	Ok(())
 }
+
+#[cfg(test)]
+#[test]
+fn check_stream_decoder() {
+	fn read(text: &str) -> Vec<u8> {
+		text.split_whitespace()
+			.map(|a| u8::from_str_radix(a, 16).unwrap())
+			.collect()
+	}
+	fn tracking_ids(frame: &[Contact]) -> Vec<u16> {
+		frame.iter().map(|a| a.tracking_id).collect()
+	}
+
+	// Recording a gesture, no backend is created.
+	let mut frontend = Frontend::new(CIdentDevice::UInput, Some("check".into()), None).unwrap();
+	let mut decoder = StreamDecoder::new();
+
+	// `captures/model1.capture`, two contacts.
+	let touch = read("a5 11 0f 00 00 10 04 31 04 10 50 00 11 51 f2 1c 08 80 00 5a 5a");
+	let push = |frontend: &mut Frontend, decoder: &mut StreamDecoder, data: Vec<u8>| {
+		decoder.push(frontend, StreamEvent::Data(data)).unwrap()
+	};
+	assert!(push(&mut frontend, &mut decoder, touch.clone()).is_continue());
+	let ids = tracking_ids(&decoder.frame);
+	assert_eq!(ids.len(), 2);
+
+	// `captures/model2.capture`, the info message is not a lift.
+	let info = read(
+		"a5 10 18 00 01 01 73 33 39 30 38 2d 31 35 2e 30 2e 30 00 00 00 00 b2 45 34 00 00 04 5a 5a",
+	);
+	assert!(push(&mut frontend, &mut decoder, info).is_continue());
+	assert!(!frontend.recorder.is_empty());
+
+	assert!(push(&mut frontend, &mut decoder, touch).is_continue());
+	assert_eq!(tracking_ids(&decoder.frame), ids);
+	assert!(!frontend.recorder.is_empty());
+}
@@ -1,8 +1,133 @@
 use log::trace;
 
 use crate::config::INVALID_BYTE;
+use crate::config::NEEDS_COORDINATE_INVERSION;
+use crate::config::SENSOR_MAX_X;
+use crate::config::SENSOR_MAX_Y;
+use crate::config::SENSOR_SIZE_MM;
+use crate::config::TOUCH_STATE_BITS;
 pub type TouchChunk = [u8; 7];
 
+// Sensor range in the display orientation (x - right, y - down), in raw units.
+pub const SCREEN_MAX: (u16, u16) = if NEEDS_COORDINATE_INVERSION {
+	(SENSOR_MAX_Y, SENSOR_MAX_X)
+} else {
+	(SENSOR_MAX_X, SENSOR_MAX_Y)
+};
+
+// Raw units per mm in the display orientation, see `SENSOR_SIZE_MM`.
+pub const SCREEN_RESOLUTION: (i32, i32) = (
+	resolution(SCREEN_MAX.0, SENSOR_SIZE_MM.0),
+	resolution(SCREEN_MAX.1, SENSOR_SIZE_MM.1),
+);
+
+const fn resolution(max: u16, mm: u16) -> i32 {
+	if mm == 0 {
+		return 1;
+	}
+	let a = max / mm;
+	if a == 0 { 1 } else { a as i32 }
+}
+
+/// Raw sensor point to the display orientation, see `SCREEN_MAX`.
+#[inline]
+pub const fn screen_point(x: u16, y: u16) -> (u16, u16) {
+	if NEEDS_COORDINATE_INVERSION {
+		(y, SENSOR_MAX_X.saturating_sub(x))
+	} else {
+		(x, SENSOR_MAX_Y.saturating_sub(y))
+	}
+}
+
+/// Point in the display orientation back to raw sensor coordinates, see `screen_point`.
+#[inline]
+pub const fn sensor_point(x: u16, y: u16) -> (u16, u16) {
+	if NEEDS_COORDINATE_INVERSION {
+		(SENSOR_MAX_X.saturating_sub(y), x)
+	} else {
+		(x, SENSOR_MAX_Y.saturating_sub(y))
+	}
+}
+
+/// Raw sensor delta to the display orientation.
+#[inline]
+pub const fn screen_delta(dx: i32, dy: i32) -> (i32, i32) {
+	if NEEDS_COORDINATE_INVERSION {
+		(dy, -dx)
+	} else {
+		(dx, -dy)
+	}
+}
+
+/// Role of a contact, assigned in order of appearance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContactRole {
+	#[default]
+	Primary,
+	Secondary,
+	Other,
+}
+
+impl ContactRole {
+	#[inline]
+	pub const fn is_primary(&self) -> bool {
+		matches!(self, Self::Primary)
+	}
+}
+
+/// Single contact decoded from a `TouchChunk`, in raw sensor coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Contact {
+	// Finger id reported by the controller, may be reused or invalid.
+	pub id: i8,
+	// Stable id assigned by `pipeline::ident::IdentManager`.
+	pub tracking_id: u16,
+	pub role: ContactRole,
+	pub x: u16,
+	pub y: u16,
+	// Contact size reported by the controller, in controller units.
+	pub size: u8,
+	// Finger in proximity above the sensor, not touching it.
+	pub is_hovering: bool,
+}
+
+impl Contact {
+	/// Position in the display orientation, see `SCREEN_MAX`.
+	#[inline]
+	pub const fn screen_pos(&self) -> (u16, u16) {
+		screen_point(self.x, self.y)
+	}
+
+	/// Touching contact at `x`, `y` with defaults for the rest, for tests.
+	#[cfg(test)]
+	pub fn new(tracking_id: u16, x: u16, y: u16) -> Self {
+		Self {
+			tracking_id,
+			x,
+			y,
+			..Default::default()
+		}
+	}
+
+	pub fn from_chunk(data: &TouchChunk) -> Self {
+		let id: i8 = data[1].wrapping_sub(16) as i8;
+
+		let x: u16 = u16::from_le_bytes([data[2], data[3] & 0b0000_1111]);
+		let y: u16 = u16::from_le_bytes([data[4], data[3] & 0b1111_0000]) << 4;
+
+		Self {
+			id,
+			tracking_id: 0,
+			role: ContactRole::Primary,
+			x,
+			y,
+			size: data[5],
+			// State byte, see `TOUCH_STATE_BITS`.
+			is_hovering: data[6] & TOUCH_STATE_BITS == 0,
+		}
+	}
+}
+
 pub struct BuildReader {
 	address: [u8; 2],
 	is_write_address: u8, // 0 - true, 1 - true, 2 - false,
@@ -223,6 +348,8 @@ fn check_model1() {
 				line.iter().copied(),
 				|/*address,*/ /*n_message*/ /*, n_chunk*/ data| {
 					println!("{:#01x}: {:?}", address /*, n_chunk*/, data);
+					// Every contact of the capture touches the sensor.
+					assert!(!Contact::from_chunk(data).is_hovering);
 				},
 			);
 
@@ -236,6 +363,12 @@ fn check_model1() {
 			break;
 		}
 	}
+
+	// The second finger of line 2 without its touch bit.
+	let mut chunk = [0x0, 0x11, 0x51, 0xF2, 0x1C, 0x8, 0x80];
+	assert!(!Contact::from_chunk(&chunk).is_hovering);
+	chunk[6] = 0x10;
+	assert!(Contact::from_chunk(&chunk).is_hovering);
 }
 
 #[cfg(test)]
@@ -0,0 +1,141 @@
+use crate::config::KINETIC_MIN_VELOCITY;
+use crate::config::KINETIC_SCROLL;
+use crate::config::KINETIC_TIME_CONSTANT;
+use crate::config::SCROLL_SPEED;
+use crate::model::Contact;
+use crate::mouse::MouseEvent;
+use log::trace;
+use std::time::Instant;
+
+/// Single-finger drag to wheel events, the content follows the finger as on a phone.
+///
+/// With `KINETIC_SCROLL` the scroll continues after release with exponentially decaying
+/// velocity, driven by `tick`.
+pub struct DragScroll {
+	last: Option<(u16, u16, Instant)>,
+	// Wheel units per second along the raw sensor axes.
+	velocity: (f32, f32),
+	acc: (f32, f32),
+
+	kinetic: Option<Instant>,
+}
+
+impl DragScroll {
+	pub const fn new() -> Self {
+		Self {
+			last: None,
+			velocity: (0.0, 0.0),
+			acc: (0.0, 0.0),
+
+			kinetic: None,
+		}
+	}
+
+	#[inline]
+	pub const fn is_animating(&self) -> bool {
+		self.kinetic.is_some()
+	}
+
+	fn push_acc(&mut self, dx: f32, dy: f32, events: &mut Vec<MouseEvent>) {
+		self.acc.0 += dx;
+		self.acc.1 += dy;
+
+		let (dx, dy) = (self.acc.0.trunc(), self.acc.1.trunc());
+		if dx != 0.0 || dy != 0.0 {
+			self.acc.0 -= dx;
+			self.acc.1 -= dy;
+			events.push(MouseEvent::Scroll {
+				dx: dx as i32,
+				dy: dy as i32,
+			});
+		}
+	}
+
+	/// Contact went down, stops any kinetic scroll.
+	pub fn start(&mut self, contact: &Contact, now: Instant) {
+		*self = Self::new();
+		self.last = Some((contact.x, contact.y, now));
+	}
+
+	pub fn push_move(&mut self, contact: &Contact, now: Instant, events: &mut Vec<MouseEvent>) {
+		let Some((lx, ly, ltime)) = self.last else {
+			self.start(contact, now);
+			return;
+		};
+		self.last = Some((contact.x, contact.y, now));
+
+		let dx = -(contact.x as f32 - lx as f32) * SCROLL_SPEED;
+		let dy = -(contact.y as f32 - ly as f32) * SCROLL_SPEED;
+		self.push_acc(dx, dy, events);
+
+		let dt = now.saturating_duration_since(ltime).as_secs_f32();
+		if dt > 0.0 {
+			// Smoothed, a single late frame must not decide the fling.
+			self.velocity.0 = self.velocity.0 * 0.5 + dx / dt * 0.5;
+			self.velocity.1 = self.velocity.1 * 0.5 + dy / dt * 0.5;
+		}
+	}
+
+	/// Contact was lifted.
+	pub fn release(&mut self, now: Instant) {
+		let speed = self.velocity.0.hypot(self.velocity.1);
+		if self.last.take().is_some() && KINETIC_SCROLL && speed >= KINETIC_MIN_VELOCITY {
+			trace!("#[drag_scroll] kinetic, velocity: {:?}", self.velocity);
+			self.kinetic = Some(now);
+		}
+	}
+
+	pub fn tick(&mut self, now: Instant, events: &mut Vec<MouseEvent>) {
+		let Some(ltime) = self.kinetic else {
+			return;
+		};
+
+		let dt = now.saturating_duration_since(ltime).as_secs_f32();
+		let decay = (-dt / KINETIC_TIME_CONSTANT.as_secs_f32()).exp();
+		self.velocity.0 *= decay;
+		self.velocity.1 *= decay;
+		self.kinetic = Some(now);
+
+		let (vx, vy) = self.velocity;
+		self.push_acc(vx * dt, vy * dt, events);
+
+		if vx.hypot(vy) < KINETIC_MIN_VELOCITY {
+			trace!("#[drag_scroll] kinetic stop");
+			*self = Self::new();
+		}
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_drag_scroll() {
+	use std::time::Duration;
+
+	let mut scroll = DragScroll::new();
+	let mut events = Vec::new();
+	let mut now = Instant::now();
+
+	scroll.start(&Contact::new(0, 100, 500), now);
+	for y in [450, 400, 350] {
+		now += Duration::from_millis(10);
+		scroll.push_move(&Contact::new(0, 100, y), now, &mut events);
+	}
+	assert_eq!(
+		events
+			.iter()
+			.map(|a| match a {
+				MouseEvent::Scroll { dy, .. } => *dy,
+				_ => 0,
+			})
+			.sum::<i32>(),
+		(150.0 * SCROLL_SPEED) as i32
+	);
+
+	scroll.release(now);
+	assert!(scroll.is_animating());
+	for _ in 0..1000 {
+		now += Duration::from_millis(16);
+		scroll.tick(now, &mut events);
+	}
+	assert!(!scroll.is_animating());
+}
@@ -0,0 +1,274 @@
+use crate::model::Contact;
+use crate::mouse::drag_scroll::DragScroll;
+use crate::mouse::scroll::TwoFingerScroll;
+use crate::mouse::tap::TapEvent;
+use crate::mouse::tap::TapRecognizer;
+use crate::mouse::touchpad::RelativeMotion;
+use log::trace;
+use std::time::Instant;
+
+pub mod drag_scroll;
+pub mod scroll;
+pub mod tap;
+pub mod touchpad;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MouseMode {
+	// Tap to click, press and drag once the contact moves, release on contact-up.
+	Drag = 1,
+	// Only move the pointer, click once all contacts are released.
+	Click = 2,
+	// Single-finger drag scrolls, only a stationary tap clicks.
+	Scroll = 3,
+	// Relative motion as on a laptop trackpad, tap to click.
+	Touchpad = 4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+	Left,
+	Right,
+	Middle,
+}
+
+/// Event produced by `MouseEmulator`, coordinates are raw sensor coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseEvent {
+	Move { x: u16, y: u16 },
+	// Relative motion in pointer units along the raw sensor axes.
+	MoveRel { dx: i32, dy: i32 },
+	Down(MouseButton),
+	Up(MouseButton),
+	Click(MouseButton),
+	// Wheel units (1/120 of a notch) along the raw sensor axes.
+	Scroll { dx: i32, dy: i32 },
+	// Ctrl+wheel notches, positive - zoom in.
+	Zoom(i32),
+}
+
+/// Single pointer emulation driven by the primary contact.
+pub struct MouseEmulator {
+	mode: MouseMode,
+	tap: TapRecognizer,
+	scroll: TwoFingerScroll,
+	drag_scroll: DragScroll,
+	touchpad: RelativeMotion,
+
+	// Tracking id of the contact driving the pointer.
+	primary: Option<u16>,
+	last: Option<(u16, u16)>,
+
+	is_pressed: bool,
+}
+
+impl MouseEmulator {
+	pub const fn new(mode: MouseMode) -> Self {
+		Self {
+			mode,
+			tap: TapRecognizer::new(),
+			scroll: TwoFingerScroll::new(),
+			drag_scroll: DragScroll::new(),
+			touchpad: RelativeMotion::new(),
+
+			primary: None,
+			last: None,
+
+			is_pressed: false,
+		}
+	}
+
+	fn push_move(&mut self, (x, y): (u16, u16), events: &mut Vec<MouseEvent>) {
+		if self.last != Some((x, y)) {
+			self.last = Some((x, y));
+			events.push(MouseEvent::Move { x, y });
+		}
+	}
+
+	fn release(&mut self, events: &mut Vec<MouseEvent>) {
+		if self.is_pressed {
+			self.is_pressed = false;
+			trace!("#[mouse] up");
+			events.push(MouseEvent::Up(MouseButton::Left));
+		}
+		self.primary = None;
+	}
+
+	fn push_tap(&mut self, tap: TapEvent, events: &mut Vec<MouseEvent>) {
+		match tap {
+			TapEvent::Tap { fingers: 1, .. } => events.push(MouseEvent::Click(MouseButton::Left)),
+			TapEvent::Tap { fingers: 2, .. } => events.push(MouseEvent::Click(MouseButton::Right)),
+			TapEvent::Tap { fingers: 3, .. } => events.push(MouseEvent::Click(MouseButton::Middle)),
+			TapEvent::Tap { .. } => {}
+			TapEvent::DoubleTap { x, y } => {
+				// Second click at the position of the first one, or it is not a double click.
+				if self.mode != MouseMode::Touchpad {
+					self.push_move((x, y), events);
+				}
+				events.push(MouseEvent::Click(MouseButton::Left));
+			}
+			TapEvent::LongPress { .. } => {
+				self.release(events);
+				events.push(MouseEvent::Click(MouseButton::Right));
+			}
+			TapEvent::Release {
+				fingers: 1,
+				is_long_pressed: false,
+			} if self.mode == MouseMode::Click => {
+				events.push(MouseEvent::Click(MouseButton::Left));
+			}
+			TapEvent::Release { .. } => {}
+		}
+	}
+
+	#[inline]
+	pub const fn mode(&self) -> MouseMode {
+		self.mode
+	}
+
+	/// Pointer motion without button of a contact above the sensor, while nothing touches it.
+	pub fn hover(&mut self, contact: &Contact, events: &mut Vec<MouseEvent>) {
+		if self.mode != MouseMode::Touchpad && self.primary.is_none() {
+			self.push_move((contact.x, contact.y), events);
+		}
+	}
+
+	/// Call `cancel` first, the current session is not finished.
+	#[inline]
+	pub const fn set_mode(&mut self, mode: MouseMode) {
+		self.mode = mode;
+	}
+
+	/// Forgets the current session, releasing a held button.
+	pub fn cancel(&mut self, events: &mut Vec<MouseEvent>) {
+		self.release(events);
+		self.tap = TapRecognizer::new();
+		self.scroll = TwoFingerScroll::new();
+		self.drag_scroll = DragScroll::new();
+	}
+
+	#[inline]
+	pub const fn is_animating(&self) -> bool {
+		self.drag_scroll.is_animating()
+	}
+
+	/// Advances animations between frames.
+	pub fn tick(&mut self, now: Instant, events: &mut Vec<MouseEvent>) {
+		self.drag_scroll.tick(now, events);
+	}
+
+	pub fn process(&mut self, frame: &[Contact], now: Instant, events: &mut Vec<MouseEvent>) {
+		let tap = self.tap.process(frame, now);
+		let is_scrolling = self.scroll.process(frame, events);
+
+		match frame.iter().find(|a| a.role.is_primary()) {
+			Some(_) if is_scrolling => self.release(events),
+			Some(contact) => {
+				if self.primary != Some(contact.tracking_id) {
+					// The previous primary contact was lifted.
+					self.release(events);
+					self.primary = Some(contact.tracking_id);
+					self.drag_scroll.start(contact, now);
+					self.touchpad.start(contact, now);
+				}
+
+				let is_dragged =
+					self.tap.fingers() == 1 && self.tap.is_moved() && !self.tap.is_long_pressed();
+				match self.mode {
+					MouseMode::Scroll if is_dragged => {
+						self.drag_scroll.push_move(contact, now, events);
+					}
+					MouseMode::Scroll => self.push_move((contact.x, contact.y), events),
+					MouseMode::Touchpad => self.touchpad.push_move(contact, now, events),
+					// Drag starts once a single contact moved far enough to not be a tap.
+					MouseMode::Drag | MouseMode::Click => {
+						if self.mode == MouseMode::Drag
+							&& !self.is_pressed && is_dragged
+							&& let Some(origin) = self.tap.origin()
+						{
+							self.push_move(origin, events);
+
+							trace!("#[mouse] down, tracking_id: {}", contact.tracking_id);
+							self.is_pressed = true;
+							events.push(MouseEvent::Down(MouseButton::Left));
+						}
+
+						self.push_move((contact.x, contact.y), events);
+					}
+				}
+			}
+			None => {
+				self.release(events);
+				if self.mode == MouseMode::Scroll {
+					self.drag_scroll.release(now);
+				}
+			}
+		}
+
+		if let Some(tap) = tap {
+			self.push_tap(tap, events);
+		}
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_mouse_drag() {
+	let mut mouse = MouseEmulator::new(MouseMode::Drag);
+	let mut events = Vec::new();
+	let mut now = Instant::now();
+
+	for (frame, ms) in [
+		(&[Contact::new(0, 10, 10)][..], 0),
+		(&[Contact::new(0, 10, 10)], 10),
+		(&[Contact::new(0, 200, 300)], 10),
+		(&[], 10),
+	] {
+		now += std::time::Duration::from_millis(ms);
+		mouse.process(frame, now, &mut events);
+	}
+	assert_eq!(
+		events,
+		[
+			MouseEvent::Move { x: 10, y: 10 },
+			MouseEvent::Down(MouseButton::Left),
+			MouseEvent::Move { x: 200, y: 300 },
+			MouseEvent::Up(MouseButton::Left),
+		]
+	);
+}
+
+#[cfg(test)]
+#[test]
+fn check_mouse_drag_hold() {
+	use crate::config::LONG_PRESS_DURATION;
+	use crate::config::TAP_MAX_DISTANCE;
+
+	let mut mouse = MouseEmulator::new(MouseMode::Drag);
+	let mut events = Vec::new();
+	let mut now = Instant::now();
+	// Past the tap distance, still within `LONG_PRESS_TOLERANCE`.
+	let x = 10 + TAP_MAX_DISTANCE + 5;
+
+	for (frame, duration) in [
+		(&[Contact::new(0, 10, 10)][..], std::time::Duration::ZERO),
+		(
+			&[Contact::new(0, x, 10)],
+			std::time::Duration::from_millis(10),
+		),
+		(&[Contact::new(0, x, 10)], LONG_PRESS_DURATION),
+		(&[], std::time::Duration::from_millis(10)),
+	] {
+		now += duration;
+		mouse.process(frame, now, &mut events);
+	}
+	assert_eq!(
+		events,
+		[
+			MouseEvent::Move { x: 10, y: 10 },
+			MouseEvent::Down(MouseButton::Left),
+			MouseEvent::Move { x, y: 10 },
+			MouseEvent::Up(MouseButton::Left),
+		]
+	);
+}
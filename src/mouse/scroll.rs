@@ -0,0 +1,175 @@
+use crate::config::NATURAL_SCROLL;
+use crate::config::PINCH_STEP;
+use crate::config::SCROLL_SPEED;
+use crate::config::TAP_MAX_DISTANCE;
+use crate::model::Contact;
+use crate::mouse::MouseEvent;
+use log::trace;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lock {
+	None,
+	Scroll,
+	Pinch,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pair {
+	ids: (u16, u16),
+	center: (f32, f32),
+	distance: f32,
+}
+
+impl Pair {
+	fn new(a: &Contact, b: &Contact) -> Self {
+		let (ax, ay, bx, by) = (a.x as f32, a.y as f32, b.x as f32, b.y as f32);
+
+		Self {
+			ids: (a.tracking_id, b.tracking_id),
+			center: ((ax + bx) / 2.0, (ay + by) / 2.0),
+			distance: (ax - bx).hypot(ay - by),
+		}
+	}
+}
+
+/// Two-finger drag to wheel events and pinch to Ctrl+wheel zoom.
+///
+/// The first movement beyond `TAP_MAX_DISTANCE` decides between scroll and pinch,
+/// the choice holds until one of the contacts is lifted.
+pub struct TwoFingerScroll {
+	start: Option<Pair>,
+	last: Option<Pair>,
+	lock: Lock,
+
+	// Not yet emitted parts of wheel units and zoom steps.
+	acc: (f32, f32, f32),
+}
+
+impl TwoFingerScroll {
+	pub const fn new() -> Self {
+		Self {
+			start: None,
+			last: None,
+			lock: Lock::None,
+
+			acc: (0.0, 0.0, 0.0),
+		}
+	}
+
+	#[allow(dead_code)]
+	#[inline]
+	pub fn is_active(&self) -> bool {
+		self.lock != Lock::None
+	}
+
+	fn reset(&mut self) {
+		*self = Self::new();
+	}
+
+	/// Returns `true` while a two-finger gesture owns the contacts.
+	pub fn process(&mut self, frame: &[Contact], events: &mut Vec<MouseEvent>) -> bool {
+		let [a, b] = frame else {
+			self.reset();
+			return false;
+		};
+
+		let pair = Pair::new(a, b);
+		let (start, last) = match (self.start, self.last) {
+			(Some(start), Some(last)) if last.ids == pair.ids => (start, last),
+			_ => {
+				self.reset();
+				self.start = Some(pair);
+				self.last = Some(pair);
+				return false;
+			}
+		};
+		self.last = Some(pair);
+
+		if self.lock == Lock::None {
+			let moved = (pair.center.0 - start.center.0).hypot(pair.center.1 - start.center.1);
+			let pinched = (pair.distance - start.distance).abs();
+			if moved.max(pinched) <= TAP_MAX_DISTANCE as f32 {
+				return false;
+			}
+
+			self.lock = if pinched > moved {
+				Lock::Pinch
+			} else {
+				Lock::Scroll
+			};
+			trace!("#[scroll] lock {:?}", self.lock);
+		}
+
+		match self.lock {
+			Lock::Scroll => {
+				// Natural scrolling moves the content with the fingers.
+				let sign = if NATURAL_SCROLL { -1.0 } else { 1.0 };
+
+				self.acc.0 += sign * (pair.center.0 - last.center.0) * SCROLL_SPEED;
+				self.acc.1 += sign * (pair.center.1 - last.center.1) * SCROLL_SPEED;
+
+				let (dx, dy) = (self.acc.0.trunc(), self.acc.1.trunc());
+				if dx != 0.0 || dy != 0.0 {
+					self.acc.0 -= dx;
+					self.acc.1 -= dy;
+					events.push(MouseEvent::Scroll {
+						dx: dx as i32,
+						dy: dy as i32,
+					});
+				}
+			}
+			Lock::Pinch => {
+				self.acc.2 += (pair.distance - last.distance) / PINCH_STEP as f32;
+
+				let steps = self.acc.2.trunc();
+				if steps != 0.0 {
+					self.acc.2 -= steps;
+					events.push(MouseEvent::Zoom(steps as i32));
+				}
+			}
+			Lock::None => {}
+		}
+
+		true
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_scroll() {
+	let mut scroll = TwoFingerScroll::new();
+	let mut events = Vec::new();
+	scroll.process(
+		&[Contact::new(0, 100, 100), Contact::new(1, 200, 100)],
+		&mut events,
+	);
+	scroll.process(
+		&[Contact::new(0, 100, 130), Contact::new(1, 200, 130)],
+		&mut events,
+	);
+	assert!(scroll.is_active());
+	scroll.process(
+		&[Contact::new(0, 100, 140), Contact::new(1, 200, 140)],
+		&mut events,
+	);
+	assert!(matches!(
+		events.last(),
+		Some(MouseEvent::Scroll { dx: 0, .. })
+	));
+
+	let mut events = Vec::new();
+	scroll.process(&[Contact::new(2, 100, 100)], &mut events);
+	assert!(!scroll.is_active());
+	scroll.process(
+		&[Contact::new(2, 100, 100), Contact::new(3, 200, 100)],
+		&mut events,
+	);
+	scroll.process(
+		&[
+			Contact::new(2, 100 - PINCH_STEP, 100),
+			Contact::new(3, 200 + PINCH_STEP, 100),
+		],
+		&mut events,
+	);
+	assert_eq!(events, [MouseEvent::Zoom(2)]);
+}
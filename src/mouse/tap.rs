@@ -0,0 +1,232 @@
+use crate::config::DOUBLE_TAP_INTERVAL;
+use crate::config::LONG_PRESS_DURATION;
+use crate::config::LONG_PRESS_TOLERANCE;
+use crate::config::TAP_MAX_DISTANCE;
+use crate::config::TAP_MAX_DURATION;
+use crate::model::Contact;
+use log::trace;
+use std::time::Duration;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapEvent {
+	// All contacts were lifted quickly without moving.
+	Tap {
+		fingers: usize,
+		x: u16,
+		y: u16,
+	},
+	// Single finger tap close to the previous one, `x`/`y` is the first tap.
+	DoubleTap {
+		x: u16,
+		y: u16,
+	},
+	// Single finger held still, reported while it is still down.
+	LongPress {
+		x: u16,
+		y: u16,
+	},
+	// All contacts were lifted and it was not a tap.
+	Release {
+		fingers: usize,
+		is_long_pressed: bool,
+	},
+}
+
+struct Session {
+	start: Instant,
+	// (tracking_id, x, y) where each contact went down.
+	origins: Vec<(u16, u16, u16)>,
+	fingers: usize,
+	// Largest distance any contact moved from where it went down.
+	max_distance: f32,
+
+	is_moved: bool,
+	is_long_pressed: bool,
+}
+
+/// Recognises taps, double taps and long presses from contact frames.
+///
+/// A session lasts from the first contact down until all contacts are lifted.
+pub struct TapRecognizer {
+	session: Option<Session>,
+	last_tap: Option<(Instant, u16, u16)>,
+}
+
+impl TapRecognizer {
+	pub const fn new() -> Self {
+		Self {
+			session: None,
+			last_tap: None,
+		}
+	}
+
+	/// Number of contacts seen in the current session.
+	#[inline]
+	pub fn fingers(&self) -> usize {
+		self.session.as_ref().map_or(0, |a| a.fingers)
+	}
+
+	#[inline]
+	pub fn is_moved(&self) -> bool {
+		self.session.as_ref().is_some_and(|a| a.is_moved)
+	}
+
+	#[inline]
+	pub fn is_long_pressed(&self) -> bool {
+		self.session.as_ref().is_some_and(|a| a.is_long_pressed)
+	}
+
+	/// Position where the primary contact of the session went down.
+	#[inline]
+	pub fn origin(&self) -> Option<(u16, u16)> {
+		self.session
+			.as_ref()
+			.and_then(|a| a.origins.first())
+			.map(|&(_, x, y)| (x, y))
+	}
+
+	#[inline]
+	fn distance((x0, y0): (u16, u16), (x1, y1): (u16, u16)) -> f32 {
+		(x0 as f32 - x1 as f32).hypot(y0 as f32 - y1 as f32)
+	}
+
+	pub fn process(&mut self, frame: &[Contact], now: Instant) -> Option<TapEvent> {
+		if frame.is_empty() {
+			let session = self.session.take()?;
+			let duration = now.saturating_duration_since(session.start);
+
+			if session.is_moved || session.is_long_pressed || duration > TAP_MAX_DURATION {
+				return Some(TapEvent::Release {
+					fingers: session.fingers,
+					is_long_pressed: session.is_long_pressed,
+				});
+			}
+
+			let (_, x, y) = session.origins[0];
+			if session.fingers == 1 {
+				if let Some((time, lx, ly)) = self.last_tap.take()
+					&& now.saturating_duration_since(time) <= DOUBLE_TAP_INTERVAL
+					&& Self::distance((x, y), (lx, ly)) <= TAP_MAX_DISTANCE as f32
+				{
+					trace!("#[tap] double tap, x: {}, y: {}", lx, ly);
+					return Some(TapEvent::DoubleTap { x: lx, y: ly });
+				}
+				self.last_tap = Some((now, x, y));
+			}
+
+			trace!("#[tap] tap, fingers: {}", session.fingers);
+			return Some(TapEvent::Tap {
+				fingers: session.fingers,
+				x,
+				y,
+			});
+		}
+
+		let session = self.session.get_or_insert_with(|| Session {
+			start: now,
+			origins: Vec::with_capacity(4),
+			fingers: 0,
+			max_distance: 0.0,
+
+			is_moved: false,
+			is_long_pressed: false,
+		});
+
+		for contact in frame.iter() {
+			let pos = (contact.x, contact.y);
+			match session
+				.origins
+				.iter()
+				.find(|(id, ..)| *id == contact.tracking_id)
+			{
+				Some(&(_, x, y)) => {
+					session.max_distance = session.max_distance.max(Self::distance((x, y), pos));
+				}
+				None => session.origins.push((contact.tracking_id, pos.0, pos.1)),
+			}
+		}
+		session.fingers = session.fingers.max(frame.len());
+		session.is_moved = session.max_distance > TAP_MAX_DISTANCE as f32;
+
+		// A drag held in place is not a long press, even within `LONG_PRESS_TOLERANCE`.
+		if !session.is_long_pressed
+			&& !session.is_moved
+			&& session.fingers == 1
+			&& session.max_distance <= LONG_PRESS_TOLERANCE as f32
+			&& LONG_PRESS_DURATION != Duration::ZERO
+			&& now.saturating_duration_since(session.start) >= LONG_PRESS_DURATION
+		{
+			session.is_long_pressed = true;
+
+			let (_, x, y) = session.origins[0];
+			trace!("#[tap] long press, x: {}, y: {}", x, y);
+			return Some(TapEvent::LongPress { x, y });
+		}
+
+		None
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_tap() {
+	let mut tap = TapRecognizer::new();
+	let mut now = Instant::now();
+	let mut step = |tap: &mut TapRecognizer, frame: &[Contact], ms| {
+		now += Duration::from_millis(ms);
+		tap.process(frame, now)
+	};
+
+	assert_eq!(step(&mut tap, &[Contact::new(0, 100, 100)], 0), None);
+	assert_eq!(
+		step(&mut tap, &[], 50),
+		Some(TapEvent::Tap {
+			fingers: 1,
+			x: 100,
+			y: 100
+		})
+	);
+	assert_eq!(step(&mut tap, &[Contact::new(1, 104, 98)], 100), None);
+	assert_eq!(
+		step(&mut tap, &[], 50),
+		Some(TapEvent::DoubleTap { x: 100, y: 100 })
+	);
+
+	// Two finger tap.
+	assert_eq!(step(&mut tap, &[Contact::new(2, 100, 100)], 1000), None);
+	assert_eq!(
+		step(
+			&mut tap,
+			&[Contact::new(2, 100, 100), Contact::new(3, 300, 100)],
+			20
+		),
+		None
+	);
+	assert_eq!(
+		step(&mut tap, &[], 50),
+		Some(TapEvent::Tap {
+			fingers: 2,
+			x: 100,
+			y: 100
+		})
+	);
+
+	// Long press, then release.
+	assert_eq!(step(&mut tap, &[Contact::new(4, 100, 100)], 1000), None);
+	assert_eq!(
+		step(
+			&mut tap,
+			&[Contact::new(4, 102, 100)],
+			LONG_PRESS_DURATION.as_millis() as _
+		),
+		Some(TapEvent::LongPress { x: 100, y: 100 })
+	);
+	assert_eq!(
+		step(&mut tap, &[], 50),
+		Some(TapEvent::Release {
+			fingers: 1,
+			is_long_pressed: true
+		})
+	);
+}
@@ -0,0 +1,98 @@
+use crate::config::TOUCHPAD_ACCEL;
+use crate::config::TOUCHPAD_ACCEL_THRESHOLD;
+use crate::config::TOUCHPAD_MAX_GAIN;
+use crate::config::TOUCHPAD_SPEED;
+use crate::model::Contact;
+use crate::mouse::MouseEvent;
+use std::time::Instant;
+
+/// Contact motion to relative pointer motion, as on a laptop trackpad.
+pub struct RelativeMotion {
+	last: Option<(u16, u16, Instant)>,
+	// Fractions of a pixel not yet emitted.
+	acc: (f32, f32),
+}
+
+impl RelativeMotion {
+	pub const fn new() -> Self {
+		Self {
+			last: None,
+			acc: (0.0, 0.0),
+		}
+	}
+
+	/// Pointer units per raw unit at `velocity` raw units per second.
+	pub fn gain(velocity: f32) -> f32 {
+		let boost = 1.0 + TOUCHPAD_ACCEL * (velocity - TOUCHPAD_ACCEL_THRESHOLD).max(0.0);
+		TOUCHPAD_SPEED * boost.min(TOUCHPAD_MAX_GAIN)
+	}
+
+	/// Contact went down, the pointer stays where it is.
+	pub fn start(&mut self, contact: &Contact, now: Instant) {
+		*self = Self::new();
+		self.last = Some((contact.x, contact.y, now));
+	}
+
+	pub fn push_move(&mut self, contact: &Contact, now: Instant, events: &mut Vec<MouseEvent>) {
+		let Some((lx, ly, ltime)) = self.last else {
+			self.start(contact, now);
+			return;
+		};
+		self.last = Some((contact.x, contact.y, now));
+
+		let (dx, dy) = (contact.x as f32 - lx as f32, contact.y as f32 - ly as f32);
+		if dx == 0.0 && dy == 0.0 {
+			return;
+		}
+		let dt = now
+			.saturating_duration_since(ltime)
+			.as_secs_f32()
+			.max(0.001);
+		let gain = Self::gain(dx.hypot(dy) / dt);
+
+		self.acc.0 += dx * gain;
+		self.acc.1 += dy * gain;
+		let (dx, dy) = (self.acc.0.trunc(), self.acc.1.trunc());
+		if dx != 0.0 || dy != 0.0 {
+			self.acc.0 -= dx;
+			self.acc.1 -= dy;
+			events.push(MouseEvent::MoveRel {
+				dx: dx as i32,
+				dy: dy as i32,
+			});
+		}
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_touchpad() {
+	use std::time::Duration;
+
+	let mut motion = RelativeMotion::new();
+	let mut events = Vec::new();
+	let mut now = Instant::now();
+
+	// The first contact does not move the pointer.
+	motion.start(&Contact::new(0, 500, 500), now);
+	motion.push_move(&Contact::new(0, 500, 500), now, &mut events);
+	assert!(events.is_empty());
+
+	// Slow motion is not accelerated, fast motion is.
+	now += Duration::from_secs(1);
+	motion.push_move(&Contact::new(0, 510, 500), now, &mut events);
+	let slow = 10.0 * RelativeMotion::gain(10.0);
+	now += Duration::from_millis(10);
+	motion.push_move(&Contact::new(0, 530, 500), now, &mut events);
+	let fast = 20.0 * RelativeMotion::gain(2000.0);
+	assert!(RelativeMotion::gain(2000.0) > RelativeMotion::gain(10.0));
+
+	let total: i32 = events
+		.iter()
+		.map(|a| match a {
+			MouseEvent::MoveRel { dx, dy: 0 } => *dx,
+			_ => panic!("{:?}", a),
+		})
+		.sum();
+	assert_eq!(total, (slow + fast).trunc() as i32);
+}
@@ -0,0 +1,626 @@
+// Network transport between a board running the decoder and a host injecting the input.
+//
+// Packet: magic "UT", version, kind, sequence number (u32 LE), payload length (u16 LE), payload.
+
+use crate::model::Contact;
+use crate::model::ContactRole;
+use crate::source::StreamEvent;
+use log::error;
+use log::info;
+use log::trace;
+use log::warn;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+use std::net::UdpSocket;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::Sender;
+use std::sync::mpsc::SyncSender;
+use std::sync::mpsc::TrySendError;
+use std::sync::mpsc::sync_channel;
+use std::thread::spawn;
+use std::time::Duration;
+use std::time::Instant;
+
+pub const NET_VERSION: u8 = 1;
+const MAGIC: [u8; 2] = *b"UT";
+const HEADER_SIZE: usize = 2 + 1 + 1 + 4 + 2;
+// Tracking id, id, role, x, y, size, flags.
+const CONTACT_SIZE: usize = 2 + 1 + 1 + 2 + 2 + 1 + 1;
+const FLAG_HOVERING: u8 = 0b0000_0001;
+
+const KIND_RAW: u8 = 0;
+const KIND_CONTACTS: u8 = 1;
+const KIND_KEEPALIVE: u8 = 2;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetTransport {
+	// Ordered and reliable, one sender at a time.
+	Tcp,
+	// Late packets are dropped, lost packets are skipped.
+	Udp,
+}
+
+/// What the board sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetPayload {
+	// Bytes as read from the controller, the receiver runs the whole pipeline.
+	Raw,
+	// Contacts as they would reach a local backend, the receiver only injects them.
+	Contacts,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet {
+	Raw(Vec<u8>),
+	Contacts {
+		address: u16,
+		frame: Vec<Contact>,
+		hover: Vec<Contact>,
+	},
+	Keepalive,
+}
+
+fn encode_header(buff: &mut Vec<u8>, kind: u8, seq: u32, len: usize) {
+	buff.clear();
+	buff.extend_from_slice(&MAGIC);
+	buff.push(NET_VERSION);
+	buff.push(kind);
+	buff.extend_from_slice(&seq.to_le_bytes());
+	buff.extend_from_slice(&(len as u16).to_le_bytes());
+}
+
+pub fn encode_raw(buff: &mut Vec<u8>, seq: u32, data: &[u8]) {
+	let data = &data[..data.len().min(u16::MAX as usize)];
+	encode_header(buff, KIND_RAW, seq, data.len());
+	buff.extend_from_slice(data);
+}
+
+pub fn encode_contacts(
+	buff: &mut Vec<u8>,
+	seq: u32,
+	address: u16,
+	frame: &[Contact],
+	hover: &[Contact],
+) {
+	let contacts = frame.iter().chain(hover.iter()).take(u8::MAX as usize);
+	let count = contacts.clone().count();
+	encode_header(buff, KIND_CONTACTS, seq, 2 + 1 + count * CONTACT_SIZE);
+	buff.extend_from_slice(&address.to_le_bytes());
+	buff.push(count as u8);
+	for a in contacts {
+		buff.extend_from_slice(&a.tracking_id.to_le_bytes());
+		buff.push(a.id as u8);
+		buff.push(match a.role {
+			ContactRole::Primary => 0,
+			ContactRole::Secondary => 1,
+			ContactRole::Other => 2,
+		});
+		buff.extend_from_slice(&a.x.to_le_bytes());
+		buff.extend_from_slice(&a.y.to_le_bytes());
+		buff.push(a.size);
+		buff.push(if a.is_hovering { FLAG_HOVERING } else { 0 });
+	}
+}
+
+pub fn encode_keepalive(buff: &mut Vec<u8>, seq: u32) {
+	encode_header(buff, KIND_KEEPALIVE, seq, 0);
+}
+
+/// (kind, sequence number, payload length).
+pub fn decode_header(header: &[u8]) -> anyhow::Result<(u8, u32, usize)> {
+	let Some(header) = header.get(..HEADER_SIZE) else {
+		anyhow::bail!("short packet, len: {}", header.len());
+	};
+	if header[..2] != MAGIC {
+		anyhow::bail!("invalid magic: {:?}", &header[..2]);
+	}
+	if header[2] != NET_VERSION {
+		anyhow::bail!(
+			"unsupported version: {}, expected: {}",
+			header[2],
+			NET_VERSION
+		);
+	}
+	let seq = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+	let len = u16::from_le_bytes([header[8], header[9]]) as usize;
+
+	Ok((header[3], seq, len))
+}
+
+pub fn decode_payload(kind: u8, payload: &[u8]) -> anyhow::Result<Packet> {
+	match kind {
+		KIND_RAW => Ok(Packet::Raw(payload.to_vec())),
+		KIND_CONTACTS => {
+			let (Some(address), Some(count)) = (payload.get(..2), payload.get(2)) else {
+				anyhow::bail!("short contacts payload, len: {}", payload.len());
+			};
+			let address = u16::from_le_bytes([address[0], address[1]]);
+			let contacts = &payload[3..];
+			if contacts.len() != *count as usize * CONTACT_SIZE {
+				anyhow::bail!(
+					"invalid contacts payload, count: {}, len: {}",
+					count,
+					contacts.len()
+				);
+			}
+
+			let mut frame = Vec::with_capacity(*count as usize);
+			let mut hover = Vec::new();
+			for a in contacts.chunks_exact(CONTACT_SIZE) {
+				let contact = Contact {
+					tracking_id: u16::from_le_bytes([a[0], a[1]]),
+					id: a[2] as i8,
+					role: match a[3] {
+						0 => ContactRole::Primary,
+						1 => ContactRole::Secondary,
+						_ => ContactRole::Other,
+					},
+					x: u16::from_le_bytes([a[4], a[5]]),
+					y: u16::from_le_bytes([a[6], a[7]]),
+					size: a[8],
+					is_hovering: a[9] & FLAG_HOVERING != 0,
+				};
+				if contact.is_hovering {
+					hover.push(contact);
+				} else {
+					frame.push(contact);
+				}
+			}
+
+			Ok(Packet::Contacts {
+				address,
+				frame,
+				hover,
+			})
+		}
+		KIND_KEEPALIVE => Ok(Packet::Keepalive),
+		a => anyhow::bail!("unknown packet kind: {}", a),
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SeqResult {
+	Next,
+	// Packets were lost in between.
+	Gap(u32),
+	// Late or duplicate.
+	Stale,
+}
+
+/// Sequence numbers of the received packets, 0 starts a new session.
+#[derive(Debug, Default)]
+struct SeqCheck {
+	last: Option<u32>,
+}
+
+impl SeqCheck {
+	fn check(&mut self, seq: u32) -> SeqResult {
+		let result = match self.last {
+			Some(last) if seq != 0 => match seq.wrapping_sub(last) {
+				1 => SeqResult::Next,
+				a if a == 0 || a > u32::MAX / 2 => return SeqResult::Stale,
+				a => SeqResult::Gap(a - 1),
+			},
+			_ => SeqResult::Next,
+		};
+		self.last = Some(seq);
+
+		result
+	}
+
+	#[inline]
+	fn reset(&mut self) {
+		self.last = None;
+	}
+}
+
+/// Received from the board.
+#[derive(Debug)]
+pub enum NetEvent {
+	// Raw bytes, gaps and link loss.
+	Stream(StreamEvent),
+	Contacts {
+		address: u16,
+		frame: Vec<Contact>,
+		hover: Vec<Contact>,
+	},
+}
+
+enum Link {
+	Tcp {
+		stream: Option<TcpStream>,
+		last_attempt: Option<Instant>,
+	},
+	Udp(UdpSocket),
+}
+
+impl Link {
+	fn send(&mut self, addr: &str, packet: &[u8], reconnect_interval: Duration) -> io::Result<()> {
+		match self {
+			Self::Tcp {
+				stream,
+				last_attempt,
+			} => {
+				if stream.is_none()
+					&& last_attempt.is_none_or(|a| a.elapsed() >= reconnect_interval)
+				{
+					*last_attempt = Some(Instant::now());
+					*stream = connect(addr, reconnect_interval)
+						.inspect(|_| info!("#[net] connected to {:?}", addr))
+						.inspect_err(|e| trace!("#[net] connect {:?}, err: {:?}", addr, e))
+						.ok();
+				}
+				let result = match stream {
+					Some(a) => a.write_all(packet),
+					// Dropped until reconnected.
+					None => Ok(()),
+				};
+				if let Err(ref e) = result {
+					error!("#[net] {:?} lost, err: {:?}", addr, e);
+					*stream = None;
+				}
+
+				result
+			}
+			Self::Udp(socket) => socket.send(packet).map(|_| ()),
+		}
+	}
+}
+
+fn connect(addr: &str, timeout: Duration) -> io::Result<TcpStream> {
+	let addr = addr
+		.to_socket_addrs()?
+		.next()
+		.ok_or_else(|| io::Error::from(io::ErrorKind::AddrNotAvailable))?;
+	let stream = TcpStream::connect_timeout(&addr, timeout)?;
+	stream.set_nodelay(true)?;
+	// A stalled receiver is a lost one.
+	stream.set_write_timeout(Some(timeout))?;
+
+	Ok(stream)
+}
+
+// Packets waiting for the sender thread, further ones are dropped.
+const SEND_QUEUE_SIZE: usize = 32;
+
+/// Backend of the board: sends frames (or the raw bytes) to a receiver, with keepalives in between
+/// so that a held contact is not released by the receiver.
+///
+/// Connecting and writing happen on a separate thread, an unreachable or stalled receiver costs
+/// the decoder dropped packets instead of blocking it.
+pub struct NetSender {
+	tx: SyncSender<Vec<u8>>,
+	payload: NetPayload,
+	seq: u32,
+}
+
+impl NetSender {
+	pub fn new(
+		addr: &str,
+		transport: NetTransport,
+		payload: NetPayload,
+		keepalive: Duration,
+		reconnect_interval: Duration,
+	) -> anyhow::Result<Self> {
+		let link = match transport {
+			NetTransport::Tcp => Link::Tcp {
+				stream: None,
+				last_attempt: None,
+			},
+			NetTransport::Udp => {
+				let socket = UdpSocket::bind("0.0.0.0:0")?;
+				socket.connect(addr)?;
+				Link::Udp(socket)
+			}
+		};
+		info!(
+			"#[net] send {:?} to {:?} over {:?}",
+			payload, addr, transport
+		);
+
+		let (tx, rx) = sync_channel(SEND_QUEUE_SIZE);
+		let addr = addr.to_string();
+		spawn(move || sender_thread(&addr, link, rx, keepalive, reconnect_interval));
+
+		Ok(Self {
+			tx,
+			payload,
+			// 0 starts a new session on the receiver.
+			seq: 0,
+		})
+	}
+
+	fn send_with(&mut self, encode: impl FnOnce(&mut Vec<u8>, u32)) {
+		let mut packet = Vec::with_capacity(64);
+		encode(&mut packet, self.seq);
+		// A dropped packet is a gap for the receiver.
+		self.seq = self.seq.wrapping_add(1).max(1);
+
+		if let Err(TrySendError::Full(..)) = self.tx.try_send(packet) {
+			trace!("#[net] queue full, packet dropped");
+		}
+	}
+
+	pub fn push_frame(&mut self, address: u16, frame: &[Contact], hover: &[Contact]) {
+		if self.payload == NetPayload::Contacts {
+			self.send_with(|buff, seq| encode_contacts(buff, seq, address, frame, hover));
+		}
+	}
+
+	pub fn push_raw(&mut self, data: &[u8]) {
+		if self.payload == NetPayload::Raw {
+			self.send_with(|buff, seq| encode_raw(buff, seq, data));
+		}
+	}
+}
+
+fn sender_thread(
+	addr: &str,
+	mut link: Link,
+	rx: Receiver<Vec<u8>>,
+	keepalive: Duration,
+	reconnect_interval: Duration,
+) {
+	let mut keepalive_packet = Vec::with_capacity(HEADER_SIZE);
+	// Not counted by the receiver, see `push_packet`.
+	encode_keepalive(&mut keepalive_packet, 0);
+
+	loop {
+		let result = match rx.recv_timeout(keepalive) {
+			Ok(packet) => link.send(addr, &packet, reconnect_interval),
+			Err(RecvTimeoutError::Timeout) => {
+				link.send(addr, &keepalive_packet, reconnect_interval)
+			}
+			Err(RecvTimeoutError::Disconnected) => break, // END CTHREAD
+		};
+		if let Err(e) = result {
+			trace!("#[net] send, err: {:?}", e);
+		}
+	}
+}
+
+/// Receiver side, feeds the packets of one board at a time to `tx`.
+pub enum NetListener {
+	Tcp(TcpListener),
+	Udp(UdpSocket),
+}
+
+impl NetListener {
+	pub fn bind(addr: &str, transport: NetTransport) -> io::Result<Self> {
+		let result = match transport {
+			NetTransport::Tcp => Self::Tcp(TcpListener::bind(addr)?),
+			NetTransport::Udp => Self::Udp(UdpSocket::bind(addr)?),
+		};
+		info!("#[net] listen {:?} over {:?}", addr, transport);
+
+		Ok(result)
+	}
+
+	#[cfg(test)]
+	pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+		match self {
+			Self::Tcp(a) => a.local_addr(),
+			Self::Udp(a) => a.local_addr(),
+		}
+	}
+
+	/// Runs until `tx` is dropped; no packet within `link_timeout` is a lost link.
+	pub fn run(self, link_timeout: Duration, tx: Sender<NetEvent>) -> io::Result<()> {
+		let mut seq = SeqCheck::default();
+		let mut buff = vec![0u8; HEADER_SIZE + u16::MAX as usize];
+		match self {
+			Self::Tcp(listener) => loop {
+				let (mut stream, peer) = listener.accept()?;
+				info!("#[net] {:?} connected", peer);
+				stream.set_read_timeout(Some(link_timeout))?;
+				seq.reset();
+
+				let e = loop {
+					if let Err(e) = stream.read_exact(&mut buff[..HEADER_SIZE]) {
+						break e.into();
+					}
+					let (kind, n, len) = match decode_header(&buff) {
+						Ok(a) => a,
+						// The stream can not be resynchronised.
+						Err(e) => break e,
+					};
+					if let Err(e) = stream.read_exact(&mut buff[HEADER_SIZE..HEADER_SIZE + len]) {
+						break e.into();
+					}
+					if !push_packet(
+						&mut seq,
+						kind,
+						n,
+						&buff[HEADER_SIZE..HEADER_SIZE + len],
+						&tx,
+					) {
+						return Ok(());
+					}
+				};
+				warn!("#[net] {:?} lost, err: {:?}", peer, e);
+				if tx.send(NetEvent::Stream(StreamEvent::Lost)).is_err() {
+					return Ok(());
+				}
+			},
+			Self::Udp(socket) => {
+				socket.set_read_timeout(Some(link_timeout))?;
+				let mut is_lost = true;
+				loop {
+					let size = match socket.recv_from(&mut buff) {
+						Ok((size, _)) => size,
+						Err(e)
+							if matches!(
+								e.kind(),
+								io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+							) =>
+						{
+							if !is_lost {
+								warn!("#[net] lost, no packets for {:?}", link_timeout);
+								is_lost = true;
+								seq.reset();
+								if tx.send(NetEvent::Stream(StreamEvent::Lost)).is_err() {
+									return Ok(());
+								}
+							}
+							continue;
+						}
+						Err(e) => return Err(e),
+					};
+					let (kind, n, _) = match decode_header(&buff[..size]) {
+						Ok(a) if HEADER_SIZE + a.2 == size => a,
+						Ok(_) => {
+							error!("#[net] invalid packet length: {}", size);
+							continue;
+						}
+						Err(e) => {
+							error!("#[net] invalid packet, err: {:?}", e);
+							continue;
+						}
+					};
+					is_lost = false;
+					if !push_packet(&mut seq, kind, n, &buff[HEADER_SIZE..size], &tx) {
+						return Ok(());
+					}
+				}
+			}
+		}
+	}
+}
+
+/// `false` once `tx` is dropped.
+fn push_packet(
+	seq: &mut SeqCheck,
+	kind: u8,
+	n: u32,
+	payload: &[u8],
+	tx: &Sender<NetEvent>,
+) -> bool {
+	// Keepalives only refresh the link, they are sent outside of the sequence.
+	if kind == KIND_KEEPALIVE {
+		return true;
+	}
+	match seq.check(n) {
+		SeqResult::Next => {}
+		SeqResult::Stale => {
+			trace!("#[net] stale packet, seq: {}", n);
+			return true;
+		}
+		SeqResult::Gap(lost) => {
+			warn!("#[net] {} packets lost before seq: {}", lost, n);
+			// A partial raw line can not be completed.
+			if tx.send(NetEvent::Stream(StreamEvent::Idle)).is_err() {
+				return false;
+			}
+		}
+	}
+
+	let event = match decode_payload(kind, payload) {
+		Ok(Packet::Raw(data)) => NetEvent::Stream(StreamEvent::Data(data)),
+		Ok(Packet::Contacts {
+			address,
+			frame,
+			hover,
+		}) => NetEvent::Contacts {
+			address,
+			frame,
+			hover,
+		},
+		Ok(Packet::Keepalive) => return true,
+		Err(e) => {
+			error!("#[net] invalid packet, err: {:?}", e);
+			return true;
+		}
+	};
+
+	tx.send(event).is_ok()
+}
+
+#[cfg(test)]
+#[test]
+fn check_net() {
+	use std::sync::mpsc::channel;
+
+	let contact = |tracking_id, is_hovering| Contact {
+		id: 2,
+		role: ContactRole::Secondary,
+		size: 9,
+		is_hovering,
+		..Contact::new(tracking_id, 100, 700)
+	};
+	let mut buff = Vec::new();
+	encode_contacts(
+		&mut buff,
+		7,
+		0x4B,
+		&[contact(1, false)],
+		&[contact(2, true)],
+	);
+	let (kind, seq, len) = decode_header(&buff).unwrap();
+	assert_eq!((seq, len), (7, buff.len() - HEADER_SIZE));
+	assert_eq!(
+		decode_payload(kind, &buff[HEADER_SIZE..]).unwrap(),
+		Packet::Contacts {
+			address: 0x4B,
+			frame: vec![contact(1, false)],
+			hover: vec![contact(2, true)],
+		}
+	);
+	buff[2] = NET_VERSION + 1;
+	assert!(decode_header(&buff).is_err());
+
+	let mut check = SeqCheck::default();
+	assert_eq!(check.check(5), SeqResult::Next);
+	assert_eq!(check.check(6), SeqResult::Next);
+	assert_eq!(check.check(9), SeqResult::Gap(2));
+	assert_eq!(check.check(8), SeqResult::Stale);
+	assert_eq!(check.check(0), SeqResult::Next);
+
+	// Loopback, the link is lost once the sender is dropped.
+	let listener = NetListener::bind("127.0.0.1:0", NetTransport::Tcp).unwrap();
+	let addr = listener.local_addr().unwrap().to_string();
+	let (tx, rx) = channel();
+	spawn(move || listener.run(Duration::from_secs(5), tx));
+
+	let mut sender = NetSender::new(
+		&addr,
+		NetTransport::Tcp,
+		NetPayload::Raw,
+		Duration::from_secs(5),
+		Duration::from_secs(1),
+	)
+	.unwrap();
+	sender.push_frame(0x4B, &[contact(1, false)], &[]);
+	sender.push_raw(&[0x4B, 0x00, 0]);
+	drop(sender);
+
+	// An unreachable receiver does not block the decoder.
+	let mut sender = NetSender::new(
+		"10.255.255.1:7373",
+		NetTransport::Tcp,
+		NetPayload::Contacts,
+		Duration::from_secs(5),
+		Duration::from_secs(1),
+	)
+	.unwrap();
+	let start = Instant::now();
+	for _ in 0..SEND_QUEUE_SIZE * 4 {
+		sender.push_frame(0x4B, &[contact(1, false)], &[]);
+	}
+	assert!(start.elapsed() < Duration::from_millis(200));
+
+	let timeout = Duration::from_secs(5);
+	assert!(matches!(
+		rx.recv_timeout(timeout).unwrap(),
+		NetEvent::Stream(StreamEvent::Data(a)) if a == [0x4B, 0x00, 0]
+	));
+	assert!(matches!(
+		rx.recv_timeout(timeout).unwrap(),
+		NetEvent::Stream(StreamEvent::Lost)
+	));
+}
@@ -0,0 +1,201 @@
+use crate::config::PEN_PRESSURE_CURVE;
+use crate::config::PEN_SIZE_RANGE;
+use crate::model::Contact;
+use crate::model::SCREEN_MAX;
+use crate::model::SCREEN_RESOLUTION;
+use crate::raw_uinput::RawDevice;
+use crate::raw_uinput::RawDeviceBuilder;
+use crate::raw_uinput::codes::*;
+use log::trace;
+
+pub const PRESSURE_MAX: i32 = 4095;
+
+/// Normalised contact size to normalised pressure.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub enum PressureCurve {
+	Linear,
+	// `pressure = size ^ gamma`, below 1.0 - softer, above - harder.
+	Gamma(f32),
+	// Piecewise linear through (size, pressure) points sorted by size, both in 0.0..=1.0.
+	Points(&'static [(f32, f32)]),
+}
+
+impl PressureCurve {
+	pub fn map(&self, a: f32) -> f32 {
+		let a = a.clamp(0.0, 1.0);
+		let result = match *self {
+			Self::Linear => a,
+			Self::Gamma(gamma) => a.powf(gamma),
+			Self::Points(points) => match points.iter().position(|(x, _)| *x >= a) {
+				Some(0) => points[0].1,
+				Some(i) => {
+					let ((x0, y0), (x1, y1)) = (points[i - 1], points[i]);
+					if x1 > x0 {
+						y0 + (a - x0) / (x1 - x0) * (y1 - y0)
+					} else {
+						y1
+					}
+				}
+				None => points.last().map_or(a, |(_, y)| *y),
+			},
+		};
+
+		result.clamp(0.0, 1.0)
+	}
+}
+
+/// Primary contact to pen events, the pressure follows the contact size.
+/// A hovering contact keeps the pen in proximity without touching.
+#[derive(Debug, Default)]
+pub struct PenEncoder {
+	// Tracking id of the contact driving the pen, the pen is in proximity.
+	pen: Option<u16>,
+	is_touching: bool,
+
+	// (type, code, value)
+	pub events: Vec<(u16, u16, i32)>,
+}
+
+impl PenEncoder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn pressure(size: u8, curve: PressureCurve) -> i32 {
+		let (min, max) = PEN_SIZE_RANGE;
+		let a = (size.saturating_sub(min)) as f32 / (max.saturating_sub(min)).max(1) as f32;
+
+		// A touching pen never reports zero pressure.
+		((curve.map(a) * PRESSURE_MAX as f32) as i32).max(1)
+	}
+
+	fn release(&mut self) {
+		if self.pen.take().is_some() {
+			if self.is_touching {
+				self.is_touching = false;
+				self.events.push((EV_ABS, ABS_PRESSURE, 0));
+				self.events.push((EV_KEY, BTN_TOUCH, 0));
+			}
+			self.events.push((EV_KEY, BTN_TOOL_PEN, 0));
+		}
+	}
+
+	pub fn encode(&mut self, frame: &[Contact], hover: &[Contact]) {
+		let (contact, is_touching) = match frame.iter().find(|a| a.role.is_primary()) {
+			Some(a) => (a, true),
+			None => match hover.first() {
+				Some(a) => (a, false),
+				None => {
+					self.release();
+					return;
+				}
+			},
+		};
+		if self.pen.is_some_and(|a| a != contact.tracking_id) {
+			// Another finger, lift the pen first.
+			self.release();
+		}
+
+		let (x, y) = contact.screen_pos();
+		let pressure = if is_touching {
+			Self::pressure(contact.size, PEN_PRESSURE_CURVE)
+		} else {
+			0
+		};
+		trace!("#[pen] x: {}, y: {}, pressure: {}", x, y, pressure);
+		if self.pen.is_none() {
+			self.pen = Some(contact.tracking_id);
+			self.events.push((EV_KEY, BTN_TOOL_PEN, 1));
+		}
+		self.events.push((EV_ABS, ABS_X, x as _));
+		self.events.push((EV_ABS, ABS_Y, y as _));
+		self.events.push((EV_ABS, ABS_PRESSURE, pressure));
+		if is_touching != self.is_touching {
+			self.is_touching = is_touching;
+			self.events.push((EV_KEY, BTN_TOUCH, is_touching as _));
+		}
+	}
+}
+
+/// uinput tablet for drawing and signature applications.
+pub struct PenDevice {
+	device: RawDevice,
+	encoder: PenEncoder,
+}
+
+impl PenDevice {
+	pub fn new(name: String) -> anyhow::Result<Self> {
+		let (res_x, res_y) = SCREEN_RESOLUTION;
+		let device = RawDeviceBuilder::open()?
+			.name(name)
+			.prop(INPUT_PROP_DIRECT)?
+			.key(BTN_TOOL_PEN)?
+			.key(BTN_TOUCH)?
+			.abs(ABS_X, 0, SCREEN_MAX.0 as _, res_x)?
+			.abs(ABS_Y, 0, SCREEN_MAX.1 as _, res_y)?
+			.abs(ABS_PRESSURE, 0, PRESSURE_MAX, 0)?
+			.create()?;
+
+		Ok(Self {
+			device,
+			encoder: PenEncoder::new(),
+		})
+	}
+
+	pub fn push_frame(&mut self, frame: &[Contact], hover: &[Contact]) -> anyhow::Result<()> {
+		self.encoder.encode(frame, hover);
+		if self.encoder.events.is_empty() {
+			return Ok(());
+		}
+
+		for (type_, code, value) in self.encoder.events.drain(..) {
+			self.device.write(type_, code, value);
+		}
+		self.device.synchronize()?;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_pen() {
+	let curve = PressureCurve::Points(&[(0.0, 0.0), (0.5, 0.8), (1.0, 1.0)]);
+	assert_eq!(curve.map(0.25), 0.4);
+	assert_eq!(curve.map(2.0), 1.0);
+	assert!(PressureCurve::Gamma(2.0).map(0.5) < PressureCurve::Linear.map(0.5));
+	assert_eq!(PenEncoder::pressure(0, PressureCurve::Linear), 1);
+	assert_eq!(
+		PenEncoder::pressure(PEN_SIZE_RANGE.1, PressureCurve::Linear),
+		PRESSURE_MAX
+	);
+
+	let contact = |tracking_id, size| Contact {
+		size,
+		..Contact::new(tracking_id, 100, 100)
+	};
+	let mut encoder = PenEncoder::new();
+	encoder.encode(&[contact(1, 10)], &[]);
+	assert_eq!(encoder.events.first(), Some(&(EV_KEY, BTN_TOOL_PEN, 1)));
+	assert_eq!(encoder.events.last(), Some(&(EV_KEY, BTN_TOUCH, 1)));
+	encoder.events.clear();
+
+	encoder.encode(&[contact(1, 20)], &[]);
+	assert!(!encoder.events.iter().any(|a| a.0 == EV_KEY));
+	encoder.events.clear();
+
+	// Lifted above the sensor: in proximity, not touching.
+	let hover = Contact {
+		is_hovering: true,
+		..contact(1, 0)
+	};
+	encoder.encode(&[], &[hover]);
+	assert!(encoder.events.contains(&(EV_ABS, ABS_PRESSURE, 0)));
+	assert_eq!(encoder.events.last(), Some(&(EV_KEY, BTN_TOUCH, 0)));
+	assert!(!encoder.events.contains(&(EV_KEY, BTN_TOOL_PEN, 0)));
+	encoder.events.clear();
+
+	encoder.encode(&[], &[]);
+	assert_eq!(encoder.events, [(EV_KEY, BTN_TOOL_PEN, 0)]);
+}
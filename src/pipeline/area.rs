@@ -0,0 +1,164 @@
+use crate::config::ACTIVE_AREA;
+use crate::config::CLAMP_OUT_OF_RANGE;
+use crate::config::DEAD_ZONE;
+use crate::config::EDGE_CURVES;
+use crate::config::SENSOR_MAX_X;
+use crate::config::SENSOR_MAX_Y;
+use crate::model::Contact;
+use log::trace;
+
+/// Non-linear response compensation near one edge of the sensor.
+///
+/// `lut` holds corrected distances from the edge for distances evenly spaced
+/// over `0..=band`, values in between are linearly interpolated.
+#[derive(Debug, Clone, Copy)]
+pub struct EdgeCurve {
+	pub band: u16,
+	pub lut: &'static [u16],
+}
+
+impl EdgeCurve {
+	pub const NONE: Self = Self { band: 0, lut: &[] };
+
+	#[inline]
+	pub const fn is_none(&self) -> bool {
+		self.band == 0 || self.lut.len() < 2
+	}
+
+	pub fn map(&self, dist: u16) -> u16 {
+		if self.is_none() || dist >= self.band {
+			return dist;
+		}
+
+		let steps = (self.lut.len() - 1) as u32;
+		let pos = (dist as u32) * steps;
+		let band = self.band as u32;
+
+		let i = (pos / band) as usize;
+		let frac = pos % band;
+		let (a, b) = (self.lut[i] as i32, self.lut[i + 1] as i32);
+
+		(a + (b - a) * (frac as i32) / (band as i32)) as u16
+	}
+}
+
+pub struct ActiveArea {
+	min_x: u16,
+	min_y: u16,
+	max_x: u16,
+	max_y: u16,
+
+	dead_zone: (u16, u16, u16, u16),
+	clamp: bool,
+	// left, top, right, bottom
+	edges: [EdgeCurve; 4],
+}
+
+impl ActiveArea {
+	#[inline]
+	pub const fn from_config() -> Self {
+		Self::new(ACTIVE_AREA, DEAD_ZONE, CLAMP_OUT_OF_RANGE, EDGE_CURVES)
+	}
+
+	pub const fn new(
+		(min_x, min_y, max_x, max_y): (u16, u16, u16, u16),
+		dead_zone: (u16, u16, u16, u16),
+		clamp: bool,
+		edges: [EdgeCurve; 4],
+	) -> Self {
+		Self {
+			min_x,
+			min_y,
+			max_x,
+			max_y,
+
+			dead_zone,
+			clamp,
+			edges,
+		}
+	}
+
+	#[inline]
+	const fn is_dead_zone(&self, x: u16, y: u16) -> bool {
+		let (left, top, right, bottom) = self.dead_zone;
+
+		x < left
+			|| y < top
+			|| x > SENSOR_MAX_X.saturating_sub(right)
+			|| y > SENSOR_MAX_Y.saturating_sub(bottom)
+	}
+
+	#[inline]
+	fn rescale(a: u16, min: u16, max: u16, out_max: u16) -> u16 {
+		if max <= min {
+			return 0;
+		}
+
+		((a - min) as u32 * out_max as u32 / (max - min) as u32) as u16
+	}
+
+	#[inline]
+	fn compensate(a: u16, out_max: u16, near: &EdgeCurve, far: &EdgeCurve) -> u16 {
+		let a = near.map(a);
+		out_max.saturating_sub(far.map(out_max.saturating_sub(a)))
+	}
+
+	/// Returns `false` if the contact must be dropped.
+	pub fn apply(&self, contact: &mut Contact) -> bool {
+		let (x, y) = (contact.x, contact.y);
+		if self.is_dead_zone(x, y) {
+			trace!("#[area] dead zone, id: {}, x: {}, y: {}", contact.id, x, y);
+			return false;
+		}
+
+		let is_out_of_range = x < self.min_x || x > self.max_x || y < self.min_y || y > self.max_y;
+		if is_out_of_range && !self.clamp {
+			trace!(
+				"#[area] out of range, id: {}, x: {}, y: {}",
+				contact.id, x, y
+			);
+			return false;
+		}
+		let x = x.clamp(self.min_x, self.max_x.max(self.min_x));
+		let y = y.clamp(self.min_y, self.max_y.max(self.min_y));
+
+		let x = Self::rescale(x, self.min_x, self.max_x, SENSOR_MAX_X);
+		let y = Self::rescale(y, self.min_y, self.max_y, SENSOR_MAX_Y);
+
+		let [left, top, right, bottom] = &self.edges;
+		contact.x = Self::compensate(x, SENSOR_MAX_X, left, right);
+		contact.y = Self::compensate(y, SENSOR_MAX_Y, top, bottom);
+
+		true
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_area() {
+	let area = ActiveArea::new(
+		(10, 20, 110, 220),
+		(5, 5, 0, 0),
+		true,
+		[
+			EdgeCurve {
+				band: 100,
+				lut: &[50, 100],
+			},
+			EdgeCurve::NONE,
+			EdgeCurve::NONE,
+			EdgeCurve::NONE,
+		],
+	);
+
+	let mut contact = Contact::new(0, 2, 50);
+	assert!(!area.apply(&mut contact)); // dead zone
+
+	let mut contact = Contact::new(0, 7, 20);
+	assert!(area.apply(&mut contact)); // clamped to the left edge
+	assert_eq!((contact.x, contact.y), (50, 0));
+
+	let mut contact = Contact::new(0, 110, 220);
+	assert!(area.apply(&mut contact));
+	assert_eq!((contact.x, contact.y), (SENSOR_MAX_X, SENSOR_MAX_Y));
+}
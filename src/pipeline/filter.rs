@@ -0,0 +1,247 @@
+use crate::model::Contact;
+use std::f32::consts::PI;
+use std::time::Instant;
+
+// Upper limit for `JitterFilter::MovingAverage::len`.
+const MOVING_AVERAGE_MAX: usize = 16;
+
+/// Single stage of the per-contact jitter filter chain, see `config::JITTER_FILTERS`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub enum JitterFilter {
+	/// Holds the last position until the contact moves further than `threshold` raw units.
+	Hysteresis { threshold: u16 },
+	/// Exponential moving average, `alpha` in `0.0..=1.0` (1.0 - no filtering).
+	Exponential { alpha: f32 },
+	/// Average of the last `len` positions.
+	MovingAverage { len: usize },
+	/// 1€ filter, <https://gery.casiez.net/1euro/>.
+	OneEuro {
+		min_cutoff: f32,
+		beta: f32,
+		d_cutoff: f32,
+	},
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct LowPass {
+	value: Option<f32>,
+}
+
+impl LowPass {
+	#[inline]
+	const fn alpha(cutoff: f32, dt: f32) -> f32 {
+		let tau = 1.0 / (2.0 * PI * cutoff);
+		1.0 / (1.0 + tau / dt)
+	}
+
+	#[inline]
+	fn next(&mut self, a: f32, alpha: f32) -> f32 {
+		let result = match self.value {
+			Some(prev) => prev + alpha * (a - prev),
+			None => a,
+		};
+		self.value = Some(result);
+
+		result
+	}
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct OneEuroAxis {
+	x: LowPass,
+	dx: LowPass,
+	prev: Option<f32>,
+}
+
+impl OneEuroAxis {
+	fn next(&mut self, a: f32, dt: f32, min_cutoff: f32, beta: f32, d_cutoff: f32) -> f32 {
+		let dx = match self.prev {
+			Some(prev) if dt > 0.0 => (a - prev) / dt,
+			_ => 0.0,
+		};
+		self.prev = Some(a);
+
+		let edx = self.dx.next(dx, LowPass::alpha(d_cutoff, dt));
+		let cutoff = min_cutoff + beta * edx.abs();
+
+		self.x.next(a, LowPass::alpha(cutoff, dt))
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FilterState {
+	Hysteresis(Option<(f32, f32)>),
+	Exponential(LowPass, LowPass),
+	MovingAverage {
+		buff: [(f32, f32); MOVING_AVERAGE_MAX],
+		len: usize,
+		pos: usize,
+	},
+	OneEuro(OneEuroAxis, OneEuroAxis),
+}
+
+impl FilterState {
+	const fn new(filter: &JitterFilter) -> Self {
+		match filter {
+			JitterFilter::Hysteresis { .. } => Self::Hysteresis(None),
+			JitterFilter::Exponential { .. } => {
+				Self::Exponential(LowPass { value: None }, LowPass { value: None })
+			}
+			JitterFilter::MovingAverage { .. } => Self::MovingAverage {
+				buff: [(0.0, 0.0); MOVING_AVERAGE_MAX],
+				len: 0,
+				pos: 0,
+			},
+			JitterFilter::OneEuro { .. } => Self::OneEuro(
+				OneEuroAxis {
+					x: LowPass { value: None },
+					dx: LowPass { value: None },
+					prev: None,
+				},
+				OneEuroAxis {
+					x: LowPass { value: None },
+					dx: LowPass { value: None },
+					prev: None,
+				},
+			),
+		}
+	}
+
+	fn next(&mut self, filter: &JitterFilter, (x, y): (f32, f32), dt: f32) -> (f32, f32) {
+		match (self, filter) {
+			(Self::Hysteresis(last), JitterFilter::Hysteresis { threshold }) => match last {
+				Some((lx, ly)) if (x - *lx).hypot(y - *ly) < *threshold as f32 => (*lx, *ly),
+				_ => {
+					*last = Some((x, y));
+					(x, y)
+				}
+			},
+			(Self::Exponential(fx, fy), JitterFilter::Exponential { alpha }) => {
+				(fx.next(x, *alpha), fy.next(y, *alpha))
+			}
+			(Self::MovingAverage { buff, len, pos }, JitterFilter::MovingAverage { len: max }) => {
+				let max = (*max).clamp(1, MOVING_AVERAGE_MAX);
+				buff[*pos] = (x, y);
+				*pos = (*pos + 1) % max;
+				*len = (*len + 1).min(max);
+
+				let (sx, sy) = buff[..*len]
+					.iter()
+					.fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+				(sx / *len as f32, sy / *len as f32)
+			}
+			(
+				Self::OneEuro(fx, fy),
+				JitterFilter::OneEuro {
+					min_cutoff,
+					beta,
+					d_cutoff,
+				},
+			) => (
+				fx.next(x, dt, *min_cutoff, *beta, *d_cutoff),
+				fy.next(y, dt, *min_cutoff, *beta, *d_cutoff),
+			),
+			_ => (x, y),
+		}
+	}
+}
+
+struct ContactFilter {
+	id: u16,
+	states: Vec<FilterState>,
+	last_time: Instant,
+}
+
+/// Jitter filter chain, keeps its own state for every contact id.
+pub struct FilterChain {
+	filters: &'static [JitterFilter],
+	contacts: Vec<ContactFilter>,
+}
+
+impl FilterChain {
+	pub const fn new(filters: &'static [JitterFilter]) -> Self {
+		Self {
+			filters,
+			contacts: Vec::new(),
+		}
+	}
+
+	pub fn process(&mut self, frame: &mut [Contact], now: Instant) {
+		if self.filters.is_empty() {
+			return;
+		}
+
+		// Contacts that are not in the frame anymore were released.
+		self.contacts
+			.retain(|a| frame.iter().any(|contact| contact.tracking_id == a.id));
+
+		for contact in frame.iter_mut() {
+			let state = match self
+				.contacts
+				.iter_mut()
+				.position(|a| a.id == contact.tracking_id)
+			{
+				Some(i) => &mut self.contacts[i],
+				None => {
+					self.contacts.push(ContactFilter {
+						id: contact.tracking_id,
+						states: self.filters.iter().map(FilterState::new).collect(),
+						last_time: now,
+					});
+					self.contacts.last_mut().unwrap()
+				}
+			};
+
+			// 1€ filter needs a non-zero period, the first sample is passed through anyway.
+			let dt = now
+				.saturating_duration_since(state.last_time)
+				.as_secs_f32()
+				.max(0.001);
+			state.last_time = now;
+
+			let mut point = (contact.x as f32, contact.y as f32);
+			for (state, filter) in state.states.iter_mut().zip(self.filters) {
+				point = state.next(filter, point, dt);
+			}
+
+			contact.x = point.0.round().max(0.0) as u16;
+			contact.y = point.1.round().max(0.0) as u16;
+		}
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_filter() {
+	use std::time::Duration;
+
+	let mut chain = FilterChain::new(&[
+		JitterFilter::Hysteresis { threshold: 4 },
+		JitterFilter::OneEuro {
+			min_cutoff: 1.0,
+			beta: 0.0,
+			d_cutoff: 1.0,
+		},
+	]);
+
+	let mut now = Instant::now();
+	let mut frame = [Contact::new(0, 100, 100)];
+	chain.process(&mut frame, now);
+	assert_eq!((frame[0].x, frame[0].y), (100, 100));
+
+	// Jitter below the threshold is swallowed.
+	for (x, y) in [(102, 99), (98, 101), (101, 102)] {
+		now += Duration::from_millis(10);
+		frame[0].x = x;
+		frame[0].y = y;
+		chain.process(&mut frame, now);
+		assert_eq!((frame[0].x, frame[0].y), (100, 100));
+	}
+
+	// A real movement gets through, smoothed.
+	now += Duration::from_millis(10);
+	frame[0].x = 200;
+	chain.process(&mut frame, now);
+	assert!(frame[0].x > 100 && frame[0].x < 200);
+}
@@ -0,0 +1,193 @@
+use crate::config::GHOST_BURST;
+use crate::config::GHOST_MAX_VELOCITY;
+use crate::config::GHOST_MIN_DURATION;
+use crate::config::GHOST_MIN_FRAMES;
+use crate::model::Contact;
+use log::info;
+use log::trace;
+use std::time::Duration;
+use std::time::Instant;
+
+struct Track {
+	id: u16,
+	first_time: Instant,
+	frames: usize,
+
+	last: (u16, u16, Instant),
+	is_confirmed: bool,
+}
+
+/// Suppresses phantom contacts: a contact is reported only after it persisted long enough,
+/// contacts jumping faster than physically possible start over, and a burst of new contacts
+/// mutes the input for a while.
+pub struct GhostSuppression {
+	min_frames: usize,
+	min_duration: Duration,
+	// raw units per second, 0 - disabled
+	max_velocity: u32,
+	// (count, window, mute)
+	burst: (usize, Duration, Duration),
+
+	tracks: Vec<Track>,
+	appeared: Vec<Instant>,
+	mute_until: Option<Instant>,
+}
+
+impl GhostSuppression {
+	#[inline]
+	pub const fn from_config() -> Self {
+		Self::new(
+			GHOST_MIN_FRAMES,
+			GHOST_MIN_DURATION,
+			GHOST_MAX_VELOCITY,
+			GHOST_BURST,
+		)
+	}
+
+	pub const fn new(
+		min_frames: usize,
+		min_duration: Duration,
+		max_velocity: u32,
+		burst: (usize, Duration, Duration),
+	) -> Self {
+		Self {
+			min_frames,
+			min_duration,
+			max_velocity,
+			burst,
+
+			tracks: Vec::new(),
+			appeared: Vec::new(),
+			mute_until: None,
+		}
+	}
+
+	fn is_burst(&mut self, now: Instant) -> bool {
+		let (count, window, mute) = self.burst;
+		if count == 0 {
+			return false;
+		}
+
+		self.appeared
+			.retain(|a| now.saturating_duration_since(*a) <= window);
+		if self.appeared.len() >= count {
+			info!(
+				"#[ghost] burst of {} contacts in {:?}, mute for {:?}",
+				self.appeared.len(),
+				window,
+				mute
+			);
+			self.appeared.clear();
+			self.mute_until = Some(now + mute);
+
+			return true;
+		}
+
+		false
+	}
+
+	pub fn process(&mut self, frame: &mut Vec<Contact>, now: Instant) {
+		// Released contacts are forgotten.
+		self.tracks
+			.retain(|a| frame.iter().any(|contact| contact.tracking_id == a.id));
+
+		for contact in frame.iter() {
+			let track = match self.tracks.iter_mut().find(|a| a.id == contact.tracking_id) {
+				Some(a) => a,
+				None => {
+					self.appeared.push(now);
+					self.tracks.push(Track {
+						id: contact.tracking_id,
+						first_time: now,
+						frames: 0,
+						last: (contact.x, contact.y, now),
+						is_confirmed: false,
+					});
+					self.tracks.last_mut().unwrap()
+				}
+			};
+
+			let (lx, ly, ltime) = track.last;
+			let dt = now.saturating_duration_since(ltime).as_secs_f32();
+			if self.max_velocity != 0 && dt > 0.0 {
+				let velocity =
+					(contact.x as f32 - lx as f32).hypot(contact.y as f32 - ly as f32) / dt;
+				if velocity > self.max_velocity as f32 {
+					trace!(
+						"#[ghost] teleport, id: {}, velocity: {}, restart",
+						contact.tracking_id, velocity
+					);
+					track.first_time = now;
+					track.frames = 0;
+					track.is_confirmed = false;
+				}
+			}
+			track.last = (contact.x, contact.y, now);
+			track.frames += 1;
+
+			if !track.is_confirmed
+				&& track.frames >= self.min_frames
+				&& now.saturating_duration_since(track.first_time) >= self.min_duration
+			{
+				track.is_confirmed = true;
+			}
+		}
+
+		if self.is_burst(now) || self.mute_until.is_some_and(|a| now < a) {
+			trace!("#[ghost] muted");
+			frame.clear();
+			return;
+		}
+		self.mute_until = None;
+
+		frame.retain(|contact| {
+			self.tracks
+				.iter()
+				.any(|a| a.id == contact.tracking_id && a.is_confirmed)
+		});
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_ghost() {
+	let mut ghost = GhostSuppression::new(
+		2,
+		Duration::ZERO,
+		10_000,
+		(4, Duration::from_millis(100), Duration::from_millis(500)),
+	);
+
+	// Single frame contacts are not reported.
+	let mut now = Instant::now();
+	let mut frame = vec![Contact::new(0, 100, 100)];
+	ghost.process(&mut frame, now);
+	assert!(frame.is_empty());
+
+	now += Duration::from_millis(10);
+	let mut frame = vec![Contact::new(0, 101, 100)];
+	ghost.process(&mut frame, now);
+	assert_eq!(frame, [Contact::new(0, 101, 100)]);
+
+	// 600 units in 10ms.
+	now += Duration::from_millis(10);
+	let mut frame = vec![Contact::new(0, 701, 100)];
+	ghost.process(&mut frame, now);
+	assert!(frame.is_empty());
+
+	// Burst of new contacts mutes everything.
+	now += Duration::from_millis(10);
+	let mut frame = vec![
+		Contact::new(0, 702, 100),
+		Contact::new(1, 10, 10),
+		Contact::new(2, 20, 20),
+		Contact::new(3, 30, 30),
+	];
+	ghost.process(&mut frame, now);
+	assert!(frame.is_empty());
+
+	now += Duration::from_millis(600);
+	let mut frame = vec![Contact::new(0, 703, 100)];
+	ghost.process(&mut frame, now);
+	assert_eq!(frame, [Contact::new(0, 703, 100)]);
+}
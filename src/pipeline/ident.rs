@@ -0,0 +1,197 @@
+use crate::config::IDENT_MAX_DISTANCE;
+use crate::model::Contact;
+use crate::model::ContactRole;
+use log::trace;
+
+#[derive(Debug, Clone, Copy)]
+struct Track {
+	id: i8,
+	tracking_id: u16,
+	x: u16,
+	y: u16,
+	is_hovering: bool,
+}
+
+/// Maps controller finger ids to stable, monotonically increasing tracking ids.
+///
+/// A contact keeps its tracking id while the controller reports it with the same finger id
+/// close to its last position, or with another finger id as the nearest unclaimed contact.
+pub struct IdentManager {
+	max_distance: u16,
+
+	// In order of appearance.
+	tracks: Vec<Track>,
+	next_tracking_id: u16,
+}
+
+impl IdentManager {
+	#[inline]
+	pub const fn from_config() -> Self {
+		Self::new(IDENT_MAX_DISTANCE)
+	}
+
+	pub const fn new(max_distance: u16) -> Self {
+		Self {
+			max_distance,
+
+			tracks: Vec::new(),
+			next_tracking_id: 0,
+		}
+	}
+
+	#[inline]
+	fn distance(track: &Track, contact: &Contact) -> f32 {
+		(track.x as f32 - contact.x as f32).hypot(track.y as f32 - contact.y as f32)
+	}
+
+	pub fn process(&mut self, frame: &mut [Contact]) {
+		let max_distance = self.max_distance as f32;
+		let mut matched: Vec<Option<usize>> = vec![None; frame.len()];
+		let mut is_claimed = vec![false; self.tracks.len()];
+
+		// Same finger id, not too far away.
+		for (contact, a_match) in frame.iter().zip(matched.iter_mut()) {
+			if let Some(i) = self.tracks.iter().enumerate().position(|(i, track)| {
+				!is_claimed[i]
+					&& track.id == contact.id
+					&& Self::distance(track, contact) <= max_distance
+			}) {
+				is_claimed[i] = true;
+				*a_match = Some(i);
+			}
+		}
+
+		// Controller changed or reused the finger id, nearest unclaimed contact.
+		for (contact, a_match) in frame.iter().zip(matched.iter_mut()) {
+			if a_match.is_some() {
+				continue;
+			}
+
+			let nearest = self
+				.tracks
+				.iter()
+				.enumerate()
+				.filter(|(i, _)| !is_claimed[*i])
+				.map(|(i, track)| (i, Self::distance(track, contact)))
+				.filter(|(_, distance)| *distance <= max_distance)
+				.min_by(|(_, a), (_, b)| a.total_cmp(b));
+			if let Some((i, _)) = nearest {
+				trace!(
+					"#[ident] finger id {} -> {}, tracking_id: {}",
+					self.tracks[i].id, contact.id, self.tracks[i].tracking_id
+				);
+				is_claimed[i] = true;
+				*a_match = Some(i);
+			}
+		}
+
+		let mut tracks = Vec::with_capacity(frame.len());
+		for (i, track) in self.tracks.iter().enumerate() {
+			if let Some(contact) = matched
+				.iter()
+				.position(|a| *a == Some(i))
+				.map(|n| &mut frame[n])
+			{
+				contact.tracking_id = track.tracking_id;
+				tracks.push(Track {
+					id: contact.id,
+					tracking_id: track.tracking_id,
+					x: contact.x,
+					y: contact.y,
+					is_hovering: contact.is_hovering,
+				});
+			}
+		}
+		for (contact, _) in frame
+			.iter_mut()
+			.zip(matched.iter())
+			.filter(|(_, a)| a.is_none())
+		{
+			contact.tracking_id = self.next_tracking_id;
+			self.next_tracking_id = self.next_tracking_id.wrapping_add(1);
+
+			tracks.push(Track {
+				id: contact.id,
+				tracking_id: contact.tracking_id,
+				x: contact.x,
+				y: contact.y,
+				is_hovering: contact.is_hovering,
+			});
+		}
+		self.tracks = tracks;
+	}
+
+	/// Roles in order of appearance of the touching contacts still in `frame`, once rejected
+	/// contacts are dropped, so that a rejected first contact does not keep the primary role.
+	pub fn assign_roles(&self, frame: &mut [Contact]) {
+		let tracking_ids: Vec<u16> = self
+			.tracks
+			.iter()
+			.filter(|a| !a.is_hovering)
+			.filter(|a| frame.iter().any(|c| c.tracking_id == a.tracking_id))
+			.map(|a| a.tracking_id)
+			.collect();
+		for contact in frame.iter_mut() {
+			contact.role = match tracking_ids.iter().position(|a| *a == contact.tracking_id) {
+				Some(0) => ContactRole::Primary,
+				Some(1) => ContactRole::Secondary,
+				_ => ContactRole::Other,
+			};
+		}
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_ident() {
+	let mut ident = IdentManager::new(50);
+	let contact = |id, x, y| Contact {
+		id,
+		..Contact::new(0, x, y)
+	};
+
+	let mut frame = [contact(0, 100, 100), contact(-3, 400, 400)];
+	ident.process(&mut frame);
+	ident.assign_roles(&mut frame);
+	assert_eq!(
+		frame.map(|a| (a.tracking_id, a.role)),
+		[(0, ContactRole::Primary), (1, ContactRole::Secondary)]
+	);
+
+	// Finger ids swapped by the controller.
+	let mut frame = [contact(-3, 105, 100), contact(0, 405, 400)];
+	ident.process(&mut frame);
+	assert_eq!(frame.map(|a| a.tracking_id), [0, 1]);
+
+	// Primary released, the same finger id reused far away.
+	let mut frame = [contact(0, 410, 400), contact(-3, 700, 100)];
+	ident.process(&mut frame);
+	ident.assign_roles(&mut frame);
+	assert_eq!(
+		frame.map(|a| (a.tracking_id, a.role)),
+		[(1, ContactRole::Primary), (2, ContactRole::Secondary)]
+	);
+
+	// A hovering contact keeps its tracking id, but not the primary role.
+	let mut frame = [
+		Contact {
+			is_hovering: true,
+			..contact(0, 410, 400)
+		},
+		contact(-3, 700, 100),
+	];
+	ident.process(&mut frame);
+	ident.assign_roles(&mut frame);
+	assert_eq!(
+		frame.map(|a| (a.tracking_id, a.role)),
+		[(1, ContactRole::Other), (2, ContactRole::Primary)]
+	);
+
+	// The first contact is rejected (palm, ghost), the remaining finger is the primary one.
+	let mut ident = IdentManager::new(50);
+	let mut frame = vec![contact(0, 100, 100), contact(1, 400, 400)];
+	ident.process(&mut frame);
+	frame.remove(0);
+	ident.assign_roles(&mut frame);
+	assert_eq!(frame[0].role, ContactRole::Primary);
+}
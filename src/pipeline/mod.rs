@@ -0,0 +1,51 @@
+use crate::config::JITTER_FILTERS;
+use crate::model::Contact;
+use crate::pipeline::area::ActiveArea;
+use crate::pipeline::filter::FilterChain;
+use crate::pipeline::ghost::GhostSuppression;
+use crate::pipeline::ident::IdentManager;
+use crate::pipeline::palm::PalmRejection;
+use std::time::Instant;
+
+pub mod area;
+pub mod filter;
+pub mod ghost;
+pub mod ident;
+pub mod palm;
+
+/// Coordinate pipeline applied to every decoded frame before it reaches `InputDevice`.
+pub struct Pipeline {
+	area: ActiveArea,
+	ident: IdentManager,
+	ghost: GhostSuppression,
+	palm: PalmRejection,
+	filters: FilterChain,
+}
+
+impl Default for Pipeline {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Pipeline {
+	pub const fn new() -> Self {
+		Self {
+			area: ActiveArea::from_config(),
+			ident: IdentManager::from_config(),
+			ghost: GhostSuppression::from_config(),
+			palm: PalmRejection::from_config(),
+			filters: FilterChain::new(JITTER_FILTERS),
+		}
+	}
+
+	pub fn process(&mut self, frame: &mut Vec<Contact>, now: Instant) {
+		frame.retain_mut(|contact| self.area.apply(contact));
+		self.ident.process(frame);
+		self.ghost.process(frame, now);
+		self.palm.process(frame);
+		self.ident.assign_roles(frame);
+		self.filters.process(frame, now);
+	}
+}
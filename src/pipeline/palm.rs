@@ -0,0 +1,201 @@
+use crate::config::MAX_CONTACTS;
+use crate::config::PALM_CLUSTER;
+use crate::config::PALM_EDGE;
+use crate::config::PALM_MAX_SIZE;
+use crate::config::SENSOR_MAX_X;
+use crate::config::SENSOR_MAX_Y;
+use crate::model::Contact;
+use log::info;
+use log::trace;
+
+/// Number of contacts rejected by each rule.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PalmCounters {
+	pub size: usize,
+	pub count: usize,
+	pub edge: usize,
+	pub cluster: usize,
+}
+
+impl PalmCounters {
+	#[inline]
+	pub const fn total(&self) -> usize {
+		self.size + self.count + self.edge + self.cluster
+	}
+}
+
+/// Rejects palms and forearms, a rejected contact stays rejected until it is released.
+pub struct PalmRejection {
+	max_size: u8,
+	max_contacts: usize,
+	// (band, size)
+	edge: (u16, u8),
+	// (count, radius)
+	cluster: (usize, u16),
+
+	known: Vec<u16>,
+	rejected: Vec<u16>,
+	counters: PalmCounters,
+}
+
+impl PalmRejection {
+	#[inline]
+	pub const fn from_config() -> Self {
+		Self::new(PALM_MAX_SIZE, MAX_CONTACTS, PALM_EDGE, PALM_CLUSTER)
+	}
+
+	pub const fn new(
+		max_size: u8,
+		max_contacts: usize,
+		edge: (u16, u8),
+		cluster: (usize, u16),
+	) -> Self {
+		Self {
+			max_size,
+			max_contacts,
+			edge,
+			cluster,
+
+			known: Vec::new(),
+			rejected: Vec::new(),
+			counters: PalmCounters {
+				size: 0,
+				count: 0,
+				edge: 0,
+				cluster: 0,
+			},
+		}
+	}
+
+	#[allow(dead_code)]
+	#[inline]
+	pub const fn counters(&self) -> &PalmCounters {
+		&self.counters
+	}
+
+	#[inline]
+	const fn is_near_edge(&self, contact: &Contact) -> bool {
+		let band = self.edge.0;
+
+		contact.x < band
+			|| contact.y < band
+			|| contact.x > SENSOR_MAX_X.saturating_sub(band)
+			|| contact.y > SENSOR_MAX_Y.saturating_sub(band)
+	}
+
+	fn reject(&mut self, contact: &Contact, rule: &str) {
+		self.rejected.push(contact.tracking_id);
+		info!(
+			"#[palm] reject, rule: {}, id: {}, x: {}, y: {}, size: {}, total: {}",
+			rule,
+			contact.tracking_id,
+			contact.x,
+			contact.y,
+			contact.size,
+			self.counters.total()
+		);
+	}
+
+	pub fn process(&mut self, frame: &mut Vec<Contact>) {
+		// Released contacts are forgotten.
+		self.rejected
+			.retain(|id| frame.iter().any(|contact| contact.tracking_id == *id));
+		self.known
+			.retain(|id| frame.iter().any(|contact| contact.tracking_id == *id));
+
+		// A cluster of new contacts landing together is a palm.
+		let (cluster_count, radius) = self.cluster;
+		if cluster_count > 1 {
+			let is_new = |a: &Contact| !self.known.contains(&a.tracking_id);
+			let cluster: Vec<Contact> = frame
+				.iter()
+				.filter(|contact| {
+					is_new(contact)
+						&& frame
+							.iter()
+							.filter(|a| {
+								is_new(a)
+									&& (a.x as f32 - contact.x as f32)
+										.hypot(a.y as f32 - contact.y as f32)
+										<= radius as f32
+							})
+							.count() >= cluster_count
+				})
+				.copied()
+				.collect();
+			for contact in cluster.iter() {
+				self.counters.cluster += 1;
+				self.reject(contact, "cluster");
+			}
+		}
+
+		let mut accepted = 0;
+		for contact in frame.iter() {
+			if self.rejected.contains(&contact.tracking_id) {
+				continue;
+			}
+
+			if self.max_size != 0 && contact.size > self.max_size {
+				self.counters.size += 1;
+				self.reject(contact, "size");
+			} else if self.edge.0 != 0 && contact.size > self.edge.1 && self.is_near_edge(contact) {
+				self.counters.edge += 1;
+				self.reject(contact, "edge");
+			} else if accepted >= self.max_contacts {
+				self.counters.count += 1;
+				self.reject(contact, "count");
+			} else {
+				accepted += 1;
+			}
+		}
+		for contact in frame.iter() {
+			if !self.known.contains(&contact.tracking_id) {
+				self.known.push(contact.tracking_id);
+			}
+		}
+
+		frame.retain(|contact| {
+			let is_rejected = self.rejected.contains(&contact.tracking_id);
+			if is_rejected {
+				trace!("#[palm] ignore, id: {}", contact.tracking_id);
+			}
+
+			!is_rejected
+		});
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_palm() {
+	let mut palm = PalmRejection::new(40, 2, (20, 10), (3, 50));
+	let contact = |tracking_id, x, y, size| Contact {
+		size,
+		..Contact::new(tracking_id, x, y)
+	};
+
+	// Large contact, rejected until released even when it shrinks.
+	let mut frame = vec![contact(0, 100, 100, 60), contact(1, 300, 300, 8)];
+	palm.process(&mut frame);
+	assert_eq!(frame, [contact(1, 300, 300, 8)]);
+
+	let mut frame = vec![contact(0, 100, 100, 8), contact(1, 300, 300, 8)];
+	palm.process(&mut frame);
+	assert_eq!(frame, [contact(1, 300, 300, 8)]);
+
+	// Resting on the bezel.
+	let mut frame = vec![contact(1, 300, 300, 8), contact(2, 5, 300, 12)];
+	palm.process(&mut frame);
+	assert_eq!(frame, [contact(1, 300, 300, 8)]);
+
+	// Three new contacts close together.
+	let mut frame = vec![
+		contact(1, 300, 300, 8),
+		contact(3, 200, 200, 8),
+		contact(4, 210, 200, 8),
+		contact(5, 200, 220, 8),
+	];
+	palm.process(&mut frame);
+	assert_eq!(frame, [contact(1, 300, 300, 8)]);
+	assert_eq!(palm.counters().total(), 5);
+}
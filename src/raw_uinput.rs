@@ -0,0 +1,203 @@
+// Minimal uinput device through the ioctl interface, for what the `uinput` crate
+// cannot describe: input properties, resolution and arbitrary event codes.
+
+use libc::c_char;
+use libc::c_int;
+use libc::input_absinfo;
+use libc::input_event;
+use libc::input_id;
+use libc::uinput_abs_setup;
+use libc::uinput_setup;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+
+#[allow(dead_code)]
+pub mod codes {
+	pub const EV_SYN: u16 = 0x00;
+	pub const EV_KEY: u16 = 0x01;
+	pub const EV_REL: u16 = 0x02;
+	pub const EV_ABS: u16 = 0x03;
+
+	pub const SYN_REPORT: u16 = 0x00;
+	pub const SYN_DROPPED: u16 = 0x03;
+
+	pub const INPUT_PROP_POINTER: u16 = 0x00;
+	pub const INPUT_PROP_DIRECT: u16 = 0x01;
+
+	pub const BTN_LEFT: u16 = 0x110;
+	pub const BTN_RIGHT: u16 = 0x111;
+	pub const BTN_MIDDLE: u16 = 0x112;
+	pub const BTN_TOOL_PEN: u16 = 0x140;
+	pub const BTN_TOOL_FINGER: u16 = 0x145;
+	pub const BTN_TOOL_QUINTTAP: u16 = 0x148;
+	pub const BTN_TOUCH: u16 = 0x14a;
+	pub const BTN_STYLUS: u16 = 0x14b;
+	pub const BTN_TOOL_DOUBLETAP: u16 = 0x14d;
+	pub const BTN_TOOL_TRIPLETAP: u16 = 0x14e;
+	pub const BTN_TOOL_QUADTAP: u16 = 0x14f;
+
+	pub const ABS_X: u16 = 0x00;
+	pub const ABS_Y: u16 = 0x01;
+	pub const ABS_PRESSURE: u16 = 0x18;
+	pub const ABS_DISTANCE: u16 = 0x19;
+	pub const ABS_MT_SLOT: u16 = 0x2f;
+	pub const ABS_MT_TOUCH_MAJOR: u16 = 0x30;
+	pub const ABS_MT_POSITION_X: u16 = 0x35;
+	pub const ABS_MT_POSITION_Y: u16 = 0x36;
+	pub const ABS_MT_TRACKING_ID: u16 = 0x39;
+	pub const ABS_MT_PRESSURE: u16 = 0x3a;
+	pub const ABS_MT_DISTANCE: u16 = 0x3b;
+}
+
+const UINPUT_IOCTL_BASE: u32 = b'U' as u32;
+const UI_DEV_CREATE: u32 = libc::_IO(UINPUT_IOCTL_BASE, 1);
+const UI_DEV_DESTROY: u32 = libc::_IO(UINPUT_IOCTL_BASE, 2);
+const UI_DEV_SETUP: u32 = libc::_IOW::<uinput_setup>(UINPUT_IOCTL_BASE, 3);
+const UI_ABS_SETUP: u32 = libc::_IOW::<uinput_abs_setup>(UINPUT_IOCTL_BASE, 4);
+const UI_SET_EVBIT: u32 = libc::_IOW::<c_int>(UINPUT_IOCTL_BASE, 100);
+const UI_SET_KEYBIT: u32 = libc::_IOW::<c_int>(UINPUT_IOCTL_BASE, 101);
+const UI_SET_ABSBIT: u32 = libc::_IOW::<c_int>(UINPUT_IOCTL_BASE, 103);
+const UI_SET_PROPBIT: u32 = libc::_IOW::<c_int>(UINPUT_IOCTL_BASE, 110);
+
+fn ioctl_int(file: &File, request: u32, value: c_int) -> io::Result<()> {
+	// SAFETY: the request takes an int argument by value.
+	let result = unsafe { libc::ioctl(file.as_raw_fd(), request as _, value) };
+	if result < 0 {
+		return Err(io::Error::last_os_error());
+	}
+
+	Ok(())
+}
+
+fn ioctl_ptr<T>(file: &File, request: u32, value: &T) -> io::Result<()> {
+	// SAFETY: the request reads a `T`, its size is encoded in the request.
+	let result = unsafe { libc::ioctl(file.as_raw_fd(), request as _, value as *const T) };
+	if result < 0 {
+		return Err(io::Error::last_os_error());
+	}
+
+	Ok(())
+}
+
+pub struct RawDeviceBuilder {
+	file: File,
+	id: input_id,
+	name: String,
+}
+
+impl RawDeviceBuilder {
+	pub fn open() -> io::Result<Self> {
+		let file = OpenOptions::new()
+			.write(true)
+			.custom_flags(libc::O_NONBLOCK)
+			.open("/dev/uinput")?;
+
+		Ok(Self {
+			file,
+			id: input_id {
+				bustype: 0x06, // BUS_VIRTUAL
+				vendor: 0,
+				product: 0,
+				version: 0,
+			},
+			name: String::new(),
+		})
+	}
+
+	#[inline]
+	pub fn name(mut self, name: impl Into<String>) -> Self {
+		self.name = name.into();
+		self
+	}
+
+	pub fn prop(self, prop: u16) -> io::Result<Self> {
+		ioctl_int(&self.file, UI_SET_PROPBIT, prop as _)?;
+		Ok(self)
+	}
+
+	pub fn key(self, code: u16) -> io::Result<Self> {
+		ioctl_int(&self.file, UI_SET_EVBIT, codes::EV_KEY as _)?;
+		ioctl_int(&self.file, UI_SET_KEYBIT, code as _)?;
+		Ok(self)
+	}
+
+	/// Absolute axis, `resolution` is in units per mm (units per radian for angles).
+	pub fn abs(self, code: u16, min: i32, max: i32, resolution: i32) -> io::Result<Self> {
+		ioctl_int(&self.file, UI_SET_EVBIT, codes::EV_ABS as _)?;
+		ioctl_int(&self.file, UI_SET_ABSBIT, code as _)?;
+		let setup = uinput_abs_setup {
+			code,
+			absinfo: input_absinfo {
+				value: 0,
+				minimum: min,
+				maximum: max,
+				fuzz: 0,
+				flat: 0,
+				resolution,
+			},
+		};
+		ioctl_ptr(&self.file, UI_ABS_SETUP, &setup)?;
+		Ok(self)
+	}
+
+	pub fn create(self) -> io::Result<RawDevice> {
+		// SAFETY: plain C struct, all zeroes is valid.
+		let mut setup: uinput_setup = unsafe { std::mem::zeroed() };
+		setup.id = self.id;
+		for (a, b) in setup
+			.name
+			.iter_mut()
+			.zip(self.name.bytes().take(libc::UINPUT_MAX_NAME_SIZE - 1))
+		{
+			*a = b as c_char;
+		}
+
+		ioctl_ptr(&self.file, UI_DEV_SETUP, &setup)?;
+		ioctl_int(&self.file, UI_DEV_CREATE, 0)?;
+
+		Ok(RawDevice {
+			file: self.file,
+			buff: Vec::with_capacity(32),
+		})
+	}
+}
+
+/// Created uinput device, events are buffered until `synchronize`.
+pub struct RawDevice {
+	file: File,
+	buff: Vec<input_event>,
+}
+
+impl RawDevice {
+	pub fn write(&mut self, type_: u16, code: u16, value: i32) {
+		// SAFETY: plain C struct, the kernel fills in the time.
+		let mut event: input_event = unsafe { std::mem::zeroed() };
+		event.type_ = type_;
+		event.code = code;
+		event.value = value;
+		self.buff.push(event);
+	}
+
+	/// Sends the buffered events followed by `SYN_REPORT`.
+	pub fn synchronize(&mut self) -> io::Result<()> {
+		self.write(codes::EV_SYN, codes::SYN_REPORT, 0);
+
+		let size = self.buff.len() * size_of::<input_event>();
+		// SAFETY: `input_event` is a plain C struct without padding holes the kernel cares about.
+		let bytes = unsafe { std::slice::from_raw_parts(self.buff.as_ptr() as *const u8, size) };
+		let result = self.file.write_all(bytes);
+		self.buff.clear();
+
+		result
+	}
+}
+
+impl Drop for RawDevice {
+	fn drop(&mut self) {
+		let _e = ioctl_int(&self.file, UI_DEV_DESTROY, 0);
+	}
+}
@@ -0,0 +1,194 @@
+use crate::source::StreamEvent;
+use log::info;
+use log::trace;
+use std::fs::File;
+use std::fs::read_to_string;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::Duration;
+use std::time::Instant;
+
+const HEADER: &str = "# utouch_rs capture v1";
+
+/// What happened on the controller, see `CaptureWriter`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureEvent {
+	// Edge on the interrupt pin.
+	Int,
+	// Buffer of a single `i2c.read` (or a transport chunk).
+	Read(Vec<u8>),
+}
+
+/// `<microseconds since start> int` or `<microseconds since start> read <hex bytes>`.
+fn format_record(time: Duration, event: &CaptureEvent) -> String {
+	let mut result = time.as_micros().to_string();
+	match event {
+		CaptureEvent::Int => result.push_str(" int"),
+		CaptureEvent::Read(data) => {
+			result.push_str(" read");
+			for a in data {
+				result.push_str(&format!(" {:02x}", a));
+			}
+		}
+	}
+
+	result
+}
+
+/// Appends every read buffer to a capture file, flushed per record so that a crash keeps it.
+pub struct CaptureWriter {
+	file: BufWriter<File>,
+	start: Instant,
+}
+
+impl CaptureWriter {
+	pub fn create(path: &Path) -> io::Result<Self> {
+		let mut file = BufWriter::new(File::create(path)?);
+		writeln!(file, "{}", HEADER)?;
+		info!("#[capture] record to {:?}", path);
+
+		Ok(Self {
+			file,
+			start: Instant::now(),
+		})
+	}
+
+	pub fn push(&mut self, now: Instant, event: &CaptureEvent) -> io::Result<()> {
+		let time = now.saturating_duration_since(self.start);
+		writeln!(self.file, "{}", format_record(time, event))?;
+		self.file.flush()
+	}
+}
+
+/// Records of a capture file in order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Capture {
+	pub records: Vec<(Duration, CaptureEvent)>,
+}
+
+impl Capture {
+	pub fn from_text(text: &str) -> anyhow::Result<Self> {
+		let mut lines = text.lines();
+		if lines.next().map(str::trim) != Some(HEADER) {
+			anyhow::bail!("not a capture, expected: {:?}", HEADER);
+		}
+
+		let mut records = Vec::new();
+		for (n, line) in lines.enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let mut words = line.split_whitespace();
+			let time = words
+				.next()
+				.and_then(|a| a.parse::<u64>().ok())
+				.ok_or_else(|| anyhow::anyhow!("line {}: invalid time: {:?}", n + 2, line))?;
+			let event = match words.next() {
+				Some("int") => CaptureEvent::Int,
+				Some("read") => CaptureEvent::Read(
+					words
+						.map(|a| u8::from_str_radix(a, 16))
+						.collect::<Result<_, _>>()
+						.map_err(|e| anyhow::anyhow!("line {}: {}", n + 2, e))?,
+				),
+				a => anyhow::bail!("line {}: unknown record: {:?}", n + 2, a),
+			};
+			records.push((Duration::from_micros(time), event));
+		}
+
+		Ok(Self { records })
+	}
+
+	pub fn load(path: &Path) -> anyhow::Result<Self> {
+		let result = Self::from_text(&read_to_string(path)?)?;
+		info!("#[capture] {:?}, records: {}", path, result.records.len());
+
+		Ok(result)
+	}
+}
+
+/// Feeds the read buffers to `tx` at their original times divided by `speed`
+/// (0 - without delays). Timing of taps and gestures follows the replay, not the capture.
+pub fn replay(capture: Capture, speed: f32, tx: Sender<StreamEvent>) {
+	let start = Instant::now();
+	for (time, event) in capture.records {
+		if speed > 0.0 {
+			let at = start + time.div_f32(speed);
+			sleep(at.saturating_duration_since(Instant::now()));
+		}
+		match event {
+			CaptureEvent::Int => trace!("#[capture] int, {:?}", time),
+			CaptureEvent::Read(data) => {
+				if tx.send(StreamEvent::Data(data)).is_err() {
+					return; // END CTHREAD
+				}
+			}
+		}
+	}
+
+	info!("#[capture] replay done");
+	// Release whatever the capture ended with.
+	let _e = tx.send(StreamEvent::Lost);
+}
+
+#[cfg(test)]
+#[test]
+fn check_capture() {
+	use crate::model::BuildReader;
+	use crate::model::Reader;
+	use std::sync::mpsc::channel;
+
+	let records = vec![
+		(Duration::from_micros(0), CaptureEvent::Int),
+		(
+			Duration::from_micros(1250),
+			CaptureEvent::Read(vec![0xA5, 0x11, 0x00, 0x5A]),
+		),
+	];
+	let mut text = format!("{}\n", HEADER);
+	for (time, event) in records.iter() {
+		text.push_str(&format_record(*time, event));
+		text.push('\n');
+	}
+	assert_eq!(text.lines().nth(2), Some("1250 read a5 11 00 5a"));
+	assert_eq!(Capture::from_text(&text).unwrap().records, records);
+	assert!(Capture::from_text("1250 read a5").is_err());
+
+	// Captures replayed without delays, the number of decoded chunks.
+	let replay_chunks = |text: &str| {
+		let (tx, rx) = channel();
+		replay(Capture::from_text(text).unwrap(), 0.0, tx);
+
+		let mut builder = BuildReader::empty();
+		let mut chunks = 0;
+		for event in rx.iter() {
+			let StreamEvent::Data(data) = event else {
+				continue;
+			};
+			for a in data {
+				if builder.write(a).is_end_line() {
+					let (_, line, _) = builder.get_line();
+					Reader::search(line.iter().copied(), |_| chunks += 1);
+					builder.clear();
+				}
+			}
+		}
+
+		chunks
+	};
+	// `check_model1`, chunks of the six touch lines: 1 + 2 + 2 + 3 + 4 + 5.
+	assert_eq!(
+		replay_chunks(include_str!("../../captures/model1.capture")),
+		17
+	);
+	// `check_model2`, a single (non-touch) message.
+	assert_eq!(
+		replay_chunks(include_str!("../../captures/model2.capture")),
+		3
+	);
+}
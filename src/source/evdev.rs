@@ -0,0 +1,406 @@
+use crate::model::Contact;
+use crate::model::ContactRole;
+use crate::model::SCREEN_MAX;
+use crate::model::sensor_point;
+use crate::raw_uinput::codes;
+use libc::c_int;
+use libc::input_absinfo;
+use libc::input_event;
+use log::info;
+use log::warn;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::io::Read;
+use std::mem::size_of;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+// Slots tracked at most, contacts in higher slots are ignored.
+pub const EVDEV_MAX_SLOTS: usize = 16;
+
+const EVDEV_IOCTL_BASE: u32 = b'E' as u32;
+const EVIOCGRAB: u32 = libc::_IOW::<c_int>(EVDEV_IOCTL_BASE, 0x90);
+const EVIOCGNAME: u32 = libc::_IOR::<[u8; 256]>(EVDEV_IOCTL_BASE, 0x06);
+const EVIOCGMTSLOTS: u32 = libc::_IOR::<MtSlots>(EVDEV_IOCTL_BASE, 0x0a);
+
+const SYN_REPORT: (u16, u16) = (codes::EV_SYN, codes::SYN_REPORT);
+
+const fn eviocgabs(code: u16) -> u32 {
+	libc::_IOR::<input_absinfo>(EVDEV_IOCTL_BASE, 0x40 + code as u32)
+}
+
+#[repr(C)]
+struct MtSlots {
+	code: u32,
+	values: [i32; EVDEV_MAX_SLOTS],
+}
+
+fn ioctl_read<T>(file: &File, request: u32, value: &mut T) -> io::Result<()> {
+	// SAFETY: the request writes a `T`, its size is encoded in the request.
+	let result = unsafe { libc::ioctl(file.as_raw_fd(), request as _, value as *mut T) };
+	if result < 0 {
+		return Err(io::Error::last_os_error());
+	}
+
+	Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+	// -1 - empty.
+	tracking_id: i32,
+	x: i32,
+	y: i32,
+	major: i32,
+	distance: i32,
+}
+
+impl Slot {
+	const EMPTY: Self = Self {
+		tracking_id: -1,
+		x: 0,
+		y: 0,
+		major: 0,
+		distance: 0,
+	};
+}
+
+/// MT protocol B events to contact frames in raw sensor coordinates, as `Contact::from_chunk`
+/// would decode them.
+pub struct EvdevDecoder {
+	slots: [Slot; EVDEV_MAX_SLOTS],
+	// Slots of the device, up to `EVDEV_MAX_SLOTS`.
+	num_slots: usize,
+	slot: usize,
+	// (min, max) of ABS_MT_POSITION_X and ABS_MT_POSITION_Y.
+	range_x: (i32, i32),
+	range_y: (i32, i32),
+	// Events are lost until the next SYN_REPORT, the slots need `resync`.
+	is_dropped: bool,
+}
+
+impl EvdevDecoder {
+	pub fn new(num_slots: usize, range_x: (i32, i32), range_y: (i32, i32)) -> Self {
+		Self {
+			slots: [Slot::EMPTY; EVDEV_MAX_SLOTS],
+			num_slots: num_slots.min(EVDEV_MAX_SLOTS),
+			slot: 0,
+			range_x,
+			range_y,
+			is_dropped: false,
+		}
+	}
+
+	#[inline]
+	pub const fn is_dropped(&self) -> bool {
+		self.is_dropped
+	}
+
+	/// Sets the state of a slot (or the current slot for ABS_MT_SLOT) after SYN_DROPPED,
+	/// see `is_dropped`. Slots the device does not have are ignored.
+	pub fn resync(&mut self, slot: usize, code: u16, value: i32) {
+		if code == codes::ABS_MT_SLOT {
+			self.slot = value.max(0) as usize;
+		} else if let Some(a) = self.slots[..self.num_slots].get_mut(slot) {
+			Self::set(a, code, value);
+		}
+		self.is_dropped = false;
+	}
+
+	fn set(slot: &mut Slot, code: u16, value: i32) {
+		match code {
+			codes::ABS_MT_TRACKING_ID => slot.tracking_id = value,
+			codes::ABS_MT_POSITION_X => slot.x = value,
+			codes::ABS_MT_POSITION_Y => slot.y = value,
+			codes::ABS_MT_TOUCH_MAJOR => slot.major = value,
+			codes::ABS_MT_DISTANCE => slot.distance = value,
+			_ => {}
+		}
+	}
+
+	fn scale(value: i32, (min, max): (i32, i32), screen_max: u16) -> u16 {
+		if max <= min {
+			return 0;
+		}
+		let a = (value.clamp(min, max) - min) as i64 * screen_max as i64 / (max - min) as i64;
+		a as u16
+	}
+
+	/// `true` once a frame is complete, `frame` then holds its contacts.
+	pub fn push(&mut self, type_: u16, code: u16, value: i32, frame: &mut Vec<Contact>) -> bool {
+		match (type_, code) {
+			(codes::EV_SYN, codes::SYN_DROPPED) => {
+				self.is_dropped = true;
+				false
+			}
+			(codes::EV_SYN, codes::SYN_REPORT) if self.is_dropped => false,
+			(codes::EV_SYN, codes::SYN_REPORT) => {
+				frame.clear();
+				for (n, a) in self.slots.iter().enumerate() {
+					if a.tracking_id < 0 {
+						continue;
+					}
+					// The device reports in the display orientation.
+					let (x, y) = sensor_point(
+						Self::scale(a.x, self.range_x, SCREEN_MAX.0),
+						Self::scale(a.y, self.range_y, SCREEN_MAX.1),
+					);
+					frame.push(Contact {
+						id: n as i8,
+						tracking_id: 0,
+						role: ContactRole::Primary,
+						x,
+						y,
+						size: a.major.clamp(0, u8::MAX as _) as u8,
+						is_hovering: a.distance > 0,
+					});
+				}
+				true
+			}
+			_ if self.is_dropped => false,
+			(codes::EV_ABS, codes::ABS_MT_SLOT) => {
+				self.slot = value.max(0) as usize;
+				false
+			}
+			(codes::EV_ABS, code) => {
+				if let Some(a) = self.slots[..self.num_slots].get_mut(self.slot) {
+					Self::set(a, code, value);
+				}
+				false
+			}
+			_ => false,
+		}
+	}
+}
+
+/// Touchscreen with a kernel driver (`/dev/input/eventN`) as the source of contacts.
+pub struct EvdevSource {
+	reader: BufReader<File>,
+	decoder: EvdevDecoder,
+}
+
+impl EvdevSource {
+	/// With `grab` the device is not seen by anything else while open.
+	pub fn open(path: &Path, grab: bool) -> anyhow::Result<Self> {
+		let file = File::open(path)?;
+
+		let mut name = [0u8; 256];
+		ioctl_read(&file, EVIOCGNAME, &mut name)?;
+		let name = name.split(|a| *a == 0).next().unwrap_or_default();
+		info!("#[evdev] {:?}: {:?}", path, String::from_utf8_lossy(name));
+
+		let absinfo = |code| {
+			// SAFETY: plain integers, zeroed is valid.
+			let mut a: input_absinfo = unsafe { std::mem::zeroed() };
+			ioctl_read(&file, eviocgabs(code), &mut a).map(|_| a)
+		};
+		let slot = absinfo(codes::ABS_MT_SLOT)
+			.map_err(|e| anyhow::anyhow!("not a multitouch protocol B device, {}", e))?;
+		let num_slots = (slot.maximum + 1).max(0) as usize;
+		let x = absinfo(codes::ABS_MT_POSITION_X)?;
+		let y = absinfo(codes::ABS_MT_POSITION_Y)?;
+		info!(
+			"#[evdev] slots: {}, x: {}..={}, y: {}..={}",
+			num_slots, x.minimum, x.maximum, y.minimum, y.maximum
+		);
+		if num_slots > EVDEV_MAX_SLOTS {
+			warn!("#[evdev] only {} slots are tracked", EVDEV_MAX_SLOTS);
+		}
+
+		if grab {
+			// SAFETY: the request takes an int argument by value.
+			if unsafe { libc::ioctl(file.as_raw_fd(), EVIOCGRAB as _, 1 as c_int) } < 0 {
+				return Err(io::Error::last_os_error().into());
+			}
+			info!("#[evdev] grabbed");
+		}
+
+		let mut result = Self {
+			reader: BufReader::new(file),
+			decoder: EvdevDecoder::new(num_slots, (x.minimum, x.maximum), (y.minimum, y.maximum)),
+		};
+		// Contacts already touching.
+		result.resync()?;
+
+		Ok(result)
+	}
+
+	fn resync(&mut self) -> io::Result<()> {
+		let file = self.reader.get_ref();
+		// SAFETY: plain integers, zeroed is valid.
+		let mut slot: input_absinfo = unsafe { std::mem::zeroed() };
+		ioctl_read(file, eviocgabs(codes::ABS_MT_SLOT), &mut slot)?;
+		self.decoder.resync(0, codes::ABS_MT_SLOT, slot.value);
+		for code in [
+			codes::ABS_MT_POSITION_X,
+			codes::ABS_MT_POSITION_Y,
+			codes::ABS_MT_TOUCH_MAJOR,
+			codes::ABS_MT_DISTANCE,
+			codes::ABS_MT_TRACKING_ID,
+		] {
+			let mut slots = MtSlots {
+				code: code as u32,
+				values: [0; EVDEV_MAX_SLOTS],
+			};
+			ioctl_read(file, EVIOCGMTSLOTS, &mut slots)?;
+			for (n, value) in slots.values.iter().enumerate() {
+				self.decoder.resync(n, code, *value);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Blocks until the next frame.
+	pub fn read_frame(&mut self, frame: &mut Vec<Contact>) -> io::Result<()> {
+		let mut buff = [0u8; size_of::<input_event>()];
+		loop {
+			self.reader.read_exact(&mut buff)?;
+			// SAFETY: the kernel writes whole `input_event`s, any bytes are valid.
+			let event: input_event = unsafe { std::ptr::read_unaligned(buff.as_ptr() as *const _) };
+
+			if self
+				.decoder
+				.push(event.type_, event.code, event.value, frame)
+			{
+				return Ok(());
+			}
+			if self.decoder.is_dropped() && (event.type_, event.code) == SYN_REPORT {
+				warn!("#[evdev] events dropped, resync");
+				self.resync()?;
+				self.decoder.push(SYN_REPORT.0, SYN_REPORT.1, 0, frame);
+
+				return Ok(());
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_evdev() {
+	use crate::model::screen_point;
+
+	let push =
+		|decoder: &mut EvdevDecoder, events: &[(u16, u16, i32)], frame: &mut Vec<Contact>| {
+			events
+				.iter()
+				.any(|(type_, code, value)| decoder.push(*type_, *code, *value, frame))
+		};
+	let mut decoder = EvdevDecoder::new(2, (0, 1000), (0, 500));
+	let mut frame = Vec::new();
+
+	assert!(push(
+		&mut decoder,
+		&[
+			(codes::EV_ABS, codes::ABS_MT_SLOT, 1),
+			(codes::EV_ABS, codes::ABS_MT_TRACKING_ID, 7),
+			(codes::EV_ABS, codes::ABS_MT_POSITION_X, 1000),
+			(codes::EV_ABS, codes::ABS_MT_POSITION_Y, 250),
+			(codes::EV_SYN, codes::SYN_REPORT, 0),
+		],
+		&mut frame
+	));
+	assert_eq!(frame.len(), 1);
+	assert_eq!(frame[0].id, 1);
+	assert_eq!(frame[0].screen_pos(), (SCREEN_MAX.0, SCREEN_MAX.1 / 2));
+	let (x, y) = sensor_point(12, 34);
+	assert_eq!(screen_point(x, y), (12, 34));
+
+	// Events after SYN_DROPPED are lost until SYN_REPORT.
+	assert!(!push(
+		&mut decoder,
+		&[
+			(codes::EV_SYN, codes::SYN_DROPPED, 0),
+			(codes::EV_ABS, codes::ABS_MT_TRACKING_ID, -1),
+			(codes::EV_SYN, codes::SYN_REPORT, 0),
+		],
+		&mut frame
+	));
+	assert!(decoder.is_dropped());
+	decoder.resync(1, codes::ABS_MT_TRACKING_ID, -1);
+	assert!(push(
+		&mut decoder,
+		&[(codes::EV_SYN, codes::SYN_REPORT, 0)],
+		&mut frame
+	));
+	assert!(frame.is_empty());
+
+	// `EVIOCGMTSLOTS` leaves the slots the device does not have at 0.
+	for n in 0..EVDEV_MAX_SLOTS {
+		decoder.resync(n, codes::ABS_MT_TRACKING_ID, 0);
+	}
+	assert!(push(
+		&mut decoder,
+		&[(codes::EV_SYN, codes::SYN_REPORT, 0)],
+		&mut frame
+	));
+	assert_eq!(frame.len(), 2);
+}
+
+#[cfg(test)]
+#[test]
+fn check_evdev_uinput() {
+	use crate::raw_uinput::RawDeviceBuilder;
+	use std::fs::read_dir;
+	use std::thread::sleep;
+	use std::time::Duration;
+
+	if !Path::new("/dev/uinput").exists() {
+		info!("check_evdev_uinput: no /dev/uinput, skip");
+		return;
+	}
+	const NAME: &str = "utouch_rs check_evdev_uinput";
+	let mut device = RawDeviceBuilder::open()
+		.and_then(|a| {
+			a.name(NAME)
+				.prop(codes::INPUT_PROP_DIRECT)?
+				.key(codes::BTN_TOUCH)?
+				.abs(codes::ABS_MT_SLOT, 0, 3, 0)?
+				.abs(codes::ABS_MT_TRACKING_ID, 0, u16::MAX as _, 0)?
+				.abs(codes::ABS_MT_POSITION_X, 0, 1000, 0)?
+				.abs(codes::ABS_MT_POSITION_Y, 0, 500, 0)?
+				.create()
+		})
+		.unwrap();
+	// Already touching when the source is opened.
+	device.write(codes::EV_ABS, codes::ABS_MT_SLOT, 0);
+	device.write(codes::EV_ABS, codes::ABS_MT_TRACKING_ID, 5);
+	device.write(codes::EV_ABS, codes::ABS_MT_POSITION_X, 1000);
+	device.write(codes::EV_ABS, codes::ABS_MT_POSITION_Y, 250);
+	device.write(codes::EV_KEY, codes::BTN_TOUCH, 1);
+	device.synchronize().unwrap();
+
+	// The event node shows up shortly after creation.
+	let path = (0..50)
+		.find_map(|_| {
+			let result = read_dir("/dev/input").ok()?.flatten().find_map(|a| {
+				let file = File::open(a.path()).ok()?;
+				let mut name = [0u8; 256];
+				ioctl_read(&file, EVIOCGNAME, &mut name).ok()?;
+				name.starts_with(NAME.as_bytes()).then(|| a.path())
+			});
+			if result.is_none() {
+				sleep(Duration::from_millis(20));
+			}
+			result
+		})
+		.unwrap();
+	let mut source = EvdevSource::open(&path, false).unwrap();
+
+	// Only the contact of the first slot, none for the slots the device does not have.
+	let mut frame = Vec::new();
+	device.write(codes::EV_ABS, codes::ABS_MT_POSITION_Y, 500);
+	device.synchronize().unwrap();
+	source.read_frame(&mut frame).unwrap();
+	assert_eq!(frame.len(), 1);
+	assert_eq!(frame[0].id, 0);
+	assert_eq!(frame[0].screen_pos(), (SCREEN_MAX.0, SCREEN_MAX.1));
+
+	device.write(codes::EV_ABS, codes::ABS_MT_TRACKING_ID, -1);
+	device.write(codes::EV_KEY, codes::BTN_TOUCH, 0);
+	device.synchronize().unwrap();
+	source.read_frame(&mut frame).unwrap();
+	assert!(frame.is_empty());
+}
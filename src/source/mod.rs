@@ -0,0 +1,15 @@
+// Contact sources other than the I2C controller, they feed the same pipeline.
+
+pub mod capture;
+pub mod evdev;
+pub mod serial;
+
+/// Raw controller bytes from a transport, decoded by `BuildReader` as if read over I2C.
+#[derive(Debug)]
+pub enum StreamEvent {
+	Data(Vec<u8>),
+	// Nothing arrived for a while, a partial line will not be completed.
+	Idle,
+	// The transport is gone until it reconnects, held contacts are released.
+	Lost,
+}
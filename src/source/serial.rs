@@ -0,0 +1,250 @@
+use crate::source::StreamEvent;
+use libc::speed_t;
+use libc::termios;
+use log::error;
+use log::info;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::Duration;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+	None,
+	Even,
+	Odd,
+}
+
+/// Character framing of the UART.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialFraming {
+	// 5..=8.
+	pub data_bits: u8,
+	pub parity: Parity,
+	// 1 or 2.
+	pub stop_bits: u8,
+}
+
+impl SerialFraming {
+	pub const EIGHT_N1: Self = Self {
+		data_bits: 8,
+		parity: Parity::None,
+		stop_bits: 1,
+	};
+}
+
+fn baud_to_speed(baud: u32) -> Option<speed_t> {
+	let a = match baud {
+		9600 => libc::B9600,
+		19200 => libc::B19200,
+		38400 => libc::B38400,
+		57600 => libc::B57600,
+		115200 => libc::B115200,
+		230400 => libc::B230400,
+		460800 => libc::B460800,
+		500000 => libc::B500000,
+		921600 => libc::B921600,
+		1000000 => libc::B1000000,
+		2000000 => libc::B2000000,
+		3000000 => libc::B3000000,
+		4000000 => libc::B4000000,
+		_ => return None,
+	};
+
+	Some(a)
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+	if result < 0 {
+		return Err(io::Error::last_os_error());
+	}
+
+	Ok(())
+}
+
+/// Raw tty of the controller bridge (USB-CDC or a real UART).
+pub struct SerialPort {
+	file: File,
+}
+
+impl SerialPort {
+	pub fn open(path: &Path, baud: u32, framing: SerialFraming) -> anyhow::Result<Self> {
+		let speed = baud_to_speed(baud)
+			.ok_or_else(|| anyhow::anyhow!("unsupported baud rate: {}", baud))?;
+		let size = match framing.data_bits {
+			5 => libc::CS5,
+			6 => libc::CS6,
+			7 => libc::CS7,
+			8 => libc::CS8,
+			a => anyhow::bail!("unsupported data bits: {}", a),
+		};
+
+		let file = OpenOptions::new()
+			.read(true)
+			.write(true)
+			.custom_flags(libc::O_NOCTTY)
+			.open(path)?;
+		let fd = file.as_raw_fd();
+
+		// SAFETY: plain integers, zeroed is valid and is overwritten by `tcgetattr`.
+		let mut tty: termios = unsafe { std::mem::zeroed() };
+		// SAFETY: `fd` is open for the lifetime of `file`, `tty` is a valid termios.
+		unsafe {
+			check(libc::tcgetattr(fd, &mut tty))?;
+			libc::cfmakeraw(&mut tty);
+			check(libc::cfsetispeed(&mut tty, speed))?;
+			check(libc::cfsetospeed(&mut tty, speed))?;
+		}
+		tty.c_cflag &= !(libc::CSIZE | libc::PARENB | libc::PARODD | libc::CSTOPB | libc::CRTSCTS);
+		tty.c_cflag |= size | libc::CLOCAL | libc::CREAD;
+		match framing.parity {
+			Parity::None => {}
+			Parity::Even => tty.c_cflag |= libc::PARENB,
+			Parity::Odd => tty.c_cflag |= libc::PARENB | libc::PARODD,
+		}
+		if framing.stop_bits == 2 {
+			tty.c_cflag |= libc::CSTOPB;
+		}
+		// Blocking reads of whatever arrived, `read` waits with poll.
+		tty.c_cc[libc::VMIN] = 1;
+		tty.c_cc[libc::VTIME] = 0;
+		// SAFETY: see above.
+		unsafe {
+			check(libc::tcsetattr(fd, libc::TCSANOW, &tty))?;
+			// Bytes of a previous session.
+			check(libc::tcflush(fd, libc::TCIFLUSH))?;
+		}
+
+		Ok(Self { file })
+	}
+
+	/// `Ok(0)` if nothing arrived within `timeout`, an error once the port is gone.
+	pub fn read(&mut self, buff: &mut [u8], timeout: Duration) -> io::Result<usize> {
+		let mut pollfd = libc::pollfd {
+			fd: self.file.as_raw_fd(),
+			events: libc::POLLIN,
+			revents: 0,
+		};
+		// SAFETY: a single valid pollfd.
+		let result = unsafe { libc::poll(&mut pollfd, 1, timeout.as_millis() as _) };
+		match result {
+			0 => return Ok(0),
+			a if a < 0 => {
+				let e = io::Error::last_os_error();
+				if e.kind() == io::ErrorKind::Interrupted {
+					return Ok(0);
+				}
+				return Err(e);
+			}
+			_ => {}
+		}
+		if pollfd.revents & libc::POLLIN == 0 {
+			// POLLHUP, POLLERR: unplugged.
+			return Err(io::ErrorKind::BrokenPipe.into());
+		}
+
+		match self.file.read(buff)? {
+			0 => Err(io::ErrorKind::UnexpectedEof.into()),
+			a => Ok(a),
+		}
+	}
+}
+
+/// Reads the port until `tx` is dropped, reopening it every `reconnect_interval` after unplug.
+pub fn run(
+	path: &Path,
+	baud: u32,
+	framing: SerialFraming,
+	idle_timeout: Duration,
+	reconnect_interval: Duration,
+	tx: Sender<StreamEvent>,
+) {
+	let mut is_logged = false;
+	loop {
+		let mut port = match SerialPort::open(path, baud, framing) {
+			Ok(a) => {
+				info!("#[serial] {:?} open, baud: {}, {:?}", path, baud, framing);
+				is_logged = false;
+				a
+			}
+			Err(e) => {
+				if !is_logged {
+					error!("#[serial] {:?} open, err: {:?}, retrying", path, e);
+					is_logged = true;
+				}
+				sleep(reconnect_interval);
+				continue;
+			}
+		};
+
+		let mut buff = vec![0u8; 256];
+		let mut is_idle = true;
+		loop {
+			let event = match port.read(&mut buff, idle_timeout) {
+				Ok(0) if is_idle => continue,
+				Ok(0) => {
+					is_idle = true;
+					StreamEvent::Idle
+				}
+				Ok(size) => {
+					is_idle = false;
+					StreamEvent::Data(buff[..size].to_vec())
+				}
+				Err(e) => {
+					error!("#[serial] {:?} lost, err: {:?}", path, e);
+					if tx.send(StreamEvent::Lost).is_err() {
+						return;
+					}
+					break;
+				}
+			};
+			if tx.send(event).is_err() {
+				return; // END CTHREAD
+			}
+		}
+		sleep(reconnect_interval);
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_serial() {
+	use std::ffi::CStr;
+	use std::io::Write;
+	use std::os::fd::FromRawFd;
+
+	assert_eq!(baud_to_speed(115200), Some(libc::B115200));
+	assert_eq!(baud_to_speed(12345), None);
+
+	// Pseudo-terminal pair, the slave side stands for the USB-CDC port.
+	// SAFETY: plain libc calls on a fresh descriptor, checked before use.
+	let (mut master, slave) = unsafe {
+		let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+		assert!(fd >= 0);
+		assert_eq!(libc::grantpt(fd), 0);
+		assert_eq!(libc::unlockpt(fd), 0);
+		let mut name = [0 as libc::c_char; 64];
+		assert_eq!(libc::ptsname_r(fd, name.as_mut_ptr(), name.len()), 0);
+		let slave = CStr::from_ptr(name.as_ptr()).to_str().unwrap().to_string();
+
+		(File::from_raw_fd(fd), slave)
+	};
+	let mut port = SerialPort::open(Path::new(&slave), 115200, SerialFraming::EIGHT_N1).unwrap();
+
+	let mut buff = [0u8; 16];
+	assert_eq!(port.read(&mut buff, Duration::from_millis(10)).unwrap(), 0);
+	master.write_all(&[0x4B, 0x00, 2, 0, 0x5A]).unwrap();
+	let size = port.read(&mut buff, Duration::from_secs(1)).unwrap();
+	assert_eq!(buff[..size], [0x4B, 0x00, 2, 0, 0x5A]);
+
+	// Unplugged.
+	drop(master);
+	assert!(port.read(&mut buff, Duration::from_secs(1)).is_err());
+}
@@ -0,0 +1,196 @@
+use crate::model::Contact;
+use crate::model::SCREEN_MAX;
+use crate::model::SCREEN_RESOLUTION;
+use crate::raw_uinput::RawDevice;
+use crate::raw_uinput::RawDeviceBuilder;
+use crate::raw_uinput::codes::*;
+use log::trace;
+
+// Slots of the multitouch protocol B, libinput handles up to five fingers on a touchpad.
+pub const TOUCHPAD_SLOTS: usize = 5;
+
+const TOOLS: [u16; TOUCHPAD_SLOTS] = [
+	BTN_TOOL_FINGER,
+	BTN_TOOL_DOUBLETAP,
+	BTN_TOOL_TRIPLETAP,
+	BTN_TOOL_QUADTAP,
+	BTN_TOOL_QUINTTAP,
+];
+
+/// Contacts to multitouch protocol B events of a touchpad.
+#[derive(Debug, Default)]
+pub struct TouchpadEncoder {
+	slots: [Option<u16>; TOUCHPAD_SLOTS],
+	fingers: usize,
+
+	// (type, code, value)
+	pub events: Vec<(u16, u16, i32)>,
+}
+
+impl TouchpadEncoder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn encode(&mut self, frame: &[Contact]) {
+		for (slot, a) in self.slots.iter_mut().enumerate() {
+			if let Some(id) = *a
+				&& !frame.iter().any(|a| a.tracking_id == id)
+			{
+				*a = None;
+				self.events.push((EV_ABS, ABS_MT_SLOT, slot as _));
+				self.events.push((EV_ABS, ABS_MT_TRACKING_ID, -1));
+			}
+		}
+
+		let mut first = None;
+		for contact in frame.iter() {
+			let slot = match self
+				.slots
+				.iter()
+				.position(|a| *a == Some(contact.tracking_id))
+			{
+				Some(a) => a,
+				None => match self.slots.iter().position(Option::is_none) {
+					Some(a) => {
+						self.slots[a] = Some(contact.tracking_id);
+						self.events.push((EV_ABS, ABS_MT_SLOT, a as _));
+						self.events
+							.push((EV_ABS, ABS_MT_TRACKING_ID, contact.tracking_id as _));
+						a
+					}
+					None => {
+						trace!(
+							"#[touchpad] no free slot, tracking_id: {}",
+							contact.tracking_id
+						);
+						continue;
+					}
+				},
+			};
+
+			let (x, y) = contact.screen_pos();
+			first.get_or_insert((x, y));
+			self.events.push((EV_ABS, ABS_MT_SLOT, slot as _));
+			self.events.push((EV_ABS, ABS_MT_POSITION_X, x as _));
+			self.events.push((EV_ABS, ABS_MT_POSITION_Y, y as _));
+		}
+
+		// Legacy single touch and the finger count.
+		let fingers = self.slots.iter().flatten().count();
+		if fingers != self.fingers {
+			if self.fingers == 0 || fingers == 0 {
+				self.events.push((EV_KEY, BTN_TOUCH, (fingers != 0) as _));
+			}
+			if self.fingers != 0 {
+				self.events.push((EV_KEY, TOOLS[self.fingers - 1], 0));
+			}
+			if fingers != 0 {
+				self.events.push((EV_KEY, TOOLS[fingers - 1], 1));
+			}
+			self.fingers = fingers;
+		}
+		if let Some((x, y)) = first {
+			self.events.push((EV_ABS, ABS_X, x as _));
+			self.events.push((EV_ABS, ABS_Y, y as _));
+		}
+	}
+}
+
+/// uinput device libinput recognises as a touchpad: `INPUT_PROP_POINTER`, finger count tools,
+/// `BTN_LEFT` (classification only) and the size in mm, so that its gestures, palm detection and acceleration apply.
+pub struct TouchpadDevice {
+	device: RawDevice,
+	encoder: TouchpadEncoder,
+}
+
+impl TouchpadDevice {
+	pub fn new(name: String) -> anyhow::Result<Self> {
+		let (max_x, max_y) = (SCREEN_MAX.0 as i32, SCREEN_MAX.1 as i32);
+		let (res_x, res_y) = SCREEN_RESOLUTION;
+
+		let mut builder = RawDeviceBuilder::open()?
+			.name(name)
+			.prop(INPUT_PROP_POINTER)?
+			// Never emitted: libinput expects a touchpad to have a button. Without
+			// `INPUT_PROP_BUTTONPAD` it is a separate button, not a clickpad, and clicks come
+			// from libinput's tap-to-click.
+			.key(BTN_LEFT)?
+			.key(BTN_TOUCH)?;
+		for tool in TOOLS {
+			builder = builder.key(tool)?;
+		}
+		let device = builder
+			.abs(ABS_X, 0, max_x, res_x)?
+			.abs(ABS_Y, 0, max_y, res_y)?
+			.abs(ABS_MT_SLOT, 0, TOUCHPAD_SLOTS as i32 - 1, 0)?
+			.abs(ABS_MT_TRACKING_ID, 0, u16::MAX as _, 0)?
+			.abs(ABS_MT_POSITION_X, 0, max_x, res_x)?
+			.abs(ABS_MT_POSITION_Y, 0, max_y, res_y)?
+			.create()?;
+
+		Ok(Self {
+			device,
+			encoder: TouchpadEncoder::new(),
+		})
+	}
+
+	pub fn push_frame(&mut self, frame: &[Contact]) -> anyhow::Result<()> {
+		self.encoder.encode(frame);
+		if self.encoder.events.is_empty() {
+			return Ok(());
+		}
+
+		for (type_, code, value) in self.encoder.events.drain(..) {
+			self.device.write(type_, code, value);
+		}
+		self.device.synchronize()?;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+#[test]
+fn check_touchpad_encoder() {
+	let mut encoder = TouchpadEncoder::new();
+
+	encoder.encode(&[Contact::new(7, 100, 100)]);
+	let (x, y) = Contact::new(7, 100, 100).screen_pos();
+	assert_eq!(
+		encoder.events,
+		[
+			(EV_ABS, ABS_MT_SLOT, 0),
+			(EV_ABS, ABS_MT_TRACKING_ID, 7),
+			(EV_ABS, ABS_MT_SLOT, 0),
+			(EV_ABS, ABS_MT_POSITION_X, x as _),
+			(EV_ABS, ABS_MT_POSITION_Y, y as _),
+			(EV_KEY, BTN_TOUCH, 1),
+			(EV_KEY, BTN_TOOL_FINGER, 1),
+			(EV_ABS, ABS_X, x as _),
+			(EV_ABS, ABS_Y, y as _),
+		]
+	);
+	encoder.events.clear();
+
+	// Second finger takes the next slot, the tool changes.
+	encoder.encode(&[Contact::new(7, 100, 100), Contact::new(8, 200, 200)]);
+	assert!(encoder.events.contains(&(EV_ABS, ABS_MT_TRACKING_ID, 8)));
+	assert!(encoder.events.contains(&(EV_KEY, BTN_TOOL_FINGER, 0)));
+	assert!(encoder.events.contains(&(EV_KEY, BTN_TOOL_DOUBLETAP, 1)));
+	assert!(!encoder.events.iter().any(|a| a.1 == BTN_TOUCH));
+	encoder.events.clear();
+
+	// First lifted, the second keeps its slot.
+	encoder.encode(&[Contact::new(8, 200, 200)]);
+	assert_eq!(
+		encoder.events[..2],
+		[(EV_ABS, ABS_MT_SLOT, 0), (EV_ABS, ABS_MT_TRACKING_ID, -1)]
+	);
+	assert!(encoder.events.contains(&(EV_ABS, ABS_MT_SLOT, 1)));
+	encoder.events.clear();
+
+	encoder.encode(&[]);
+	assert!(encoder.events.contains(&(EV_KEY, BTN_TOUCH, 0)));
+	assert!(encoder.events.contains(&(EV_KEY, BTN_TOOL_FINGER, 0)));
+}
@@ -0,0 +1,152 @@
+use crate::hid::HidEncoder;
+use crate::hid::REPORT_ID_MAX_COUNT;
+use crate::hid::max_count_report;
+use crate::hid::report_descriptor;
+use crate::model::Contact;
+use log::error;
+use log::info;
+use log::trace;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Read;
+use std::io::Write;
+use std::thread::spawn;
+
+// linux/uhid.h, events are packed structs in native byte order.
+const UHID_DESTROY: u32 = 1;
+const UHID_START: u32 = 2;
+const UHID_STOP: u32 = 3;
+const UHID_OPEN: u32 = 4;
+const UHID_CLOSE: u32 = 5;
+const UHID_GET_REPORT: u32 = 9;
+const UHID_GET_REPORT_REPLY: u32 = 10;
+const UHID_CREATE2: u32 = 11;
+const UHID_INPUT2: u32 = 12;
+const UHID_SET_REPORT: u32 = 13;
+const UHID_SET_REPORT_REPLY: u32 = 14;
+
+const UHID_FEATURE_REPORT: u8 = 0;
+const UHID_EVENT_SIZE: usize = 4 + 4372;
+const BUS_VIRTUAL: u16 = 0x06;
+const EIO: u16 = 5;
+
+/// HID device through /dev/uhid, the kernel's hid-multitouch driver turns the reports
+/// into a touchscreen with its own quirks handling.
+pub struct UhidDevice {
+	file: File,
+	encoder: HidEncoder,
+	event: Vec<u8>,
+}
+
+impl UhidDevice {
+	pub fn new(name: String) -> anyhow::Result<Self> {
+		let mut file = OpenOptions::new()
+			.read(true)
+			.write(true)
+			.open("/dev/uhid")?;
+
+		let descriptor = report_descriptor();
+		let mut event = Vec::with_capacity(UHID_EVENT_SIZE);
+		event.extend_from_slice(&UHID_CREATE2.to_ne_bytes());
+		let mut push_str = |a: &str, size: usize| {
+			let mut buff = vec![0u8; size];
+			for (a, b) in buff.iter_mut().zip(a.bytes().take(size - 1)) {
+				*a = b;
+			}
+			event.extend_from_slice(&buff);
+		};
+		push_str(&name, 128); // name
+		push_str("utouch_rs", 64); // phys
+		push_str("", 64); // uniq
+		event.extend_from_slice(&(descriptor.len() as u16).to_ne_bytes());
+		event.extend_from_slice(&BUS_VIRTUAL.to_ne_bytes());
+		event.extend_from_slice(&0u32.to_ne_bytes()); // vendor
+		event.extend_from_slice(&0u32.to_ne_bytes()); // product
+		event.extend_from_slice(&0u32.to_ne_bytes()); // version
+		event.extend_from_slice(&0u32.to_ne_bytes()); // country
+		event.extend_from_slice(&descriptor);
+		file.write_all(&event)?;
+		info!("#[uhid] created, descriptor: {} bytes", descriptor.len());
+
+		// The kernel asks for feature reports while probing, reply from another thread.
+		let reader = file.try_clone()?;
+		spawn(move || {
+			if let Err(e) = Self::serve(reader) {
+				error!("#[uhid] read, err: {:?}", e);
+			}
+		});
+
+		Ok(Self {
+			file,
+			encoder: HidEncoder::new(),
+			event: Vec::with_capacity(UHID_EVENT_SIZE),
+		})
+	}
+
+	fn serve(mut file: File) -> anyhow::Result<()> {
+		let mut buff = vec![0u8; UHID_EVENT_SIZE];
+		loop {
+			let size = file.read(&mut buff)?;
+			if size < 4 {
+				continue;
+			}
+
+			let kind = u32::from_ne_bytes([buff[0], buff[1], buff[2], buff[3]]);
+			match kind {
+				UHID_START | UHID_STOP | UHID_OPEN | UHID_CLOSE => {
+					trace!("#[uhid] event: {}", kind);
+				}
+				UHID_GET_REPORT => {
+					// id: u32, rnum: u8, rtype: u8
+					let id = [buff[4], buff[5], buff[6], buff[7]];
+					let (rnum, rtype) = (buff[8], buff[9]);
+					trace!("#[uhid] get report, rnum: {}, rtype: {}", rnum, rtype);
+
+					let mut reply = Vec::with_capacity(16);
+					reply.extend_from_slice(&UHID_GET_REPORT_REPLY.to_ne_bytes());
+					reply.extend_from_slice(&id);
+					if rnum == REPORT_ID_MAX_COUNT && rtype == UHID_FEATURE_REPORT {
+						let report = max_count_report();
+						reply.extend_from_slice(&0u16.to_ne_bytes());
+						reply.extend_from_slice(&(report.len() as u16).to_ne_bytes());
+						reply.extend_from_slice(&report);
+					} else {
+						reply.extend_from_slice(&EIO.to_ne_bytes());
+						reply.extend_from_slice(&0u16.to_ne_bytes());
+					}
+					file.write_all(&reply)?;
+				}
+				UHID_SET_REPORT => {
+					// Nothing to configure, accept everything.
+					let mut reply = Vec::with_capacity(16);
+					reply.extend_from_slice(&UHID_SET_REPORT_REPLY.to_ne_bytes());
+					reply.extend_from_slice(&buff[4..8]);
+					reply.extend_from_slice(&0u16.to_ne_bytes());
+					file.write_all(&reply)?;
+				}
+				_ => trace!("#[uhid] ignore event: {}", kind),
+			}
+		}
+	}
+
+	pub fn push_frame(&mut self, frame: &[Contact]) -> anyhow::Result<()> {
+		let Some(report) = self.encoder.encode(frame) else {
+			return Ok(());
+		};
+
+		self.event.clear();
+		self.event.extend_from_slice(&UHID_INPUT2.to_ne_bytes());
+		self.event
+			.extend_from_slice(&(report.len() as u16).to_ne_bytes());
+		self.event.extend_from_slice(report);
+		self.file.write_all(&self.event)?;
+
+		Ok(())
+	}
+}
+
+impl Drop for UhidDevice {
+	fn drop(&mut self) {
+		let _e = self.file.write_all(&UHID_DESTROY.to_ne_bytes());
+	}
+}